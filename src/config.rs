@@ -0,0 +1,40 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+use crate::{fifo::LedgerSettings, parser::SourceEntry, types::CsvFormat};
+
+/// Configuration of the whole processing pipeline, usually loaded from the `.toml` file.
+#[derive(Debug, Deserialize)]
+pub struct Config {
+    /// Format of the output CSV files, e.g. the separator.
+    #[serde(flatten)]
+    pub csv: CsvFormat,
+    /// Settings used to process the transactions in the ledger.
+    #[serde(flatten)]
+    pub ledger: LedgerSettings,
+    /// Path to the file with daily asset prices in the base currency, used to value the open lots.
+    pub prices: Option<String>,
+    /// Timezone in which the transactions with a known time are dated for reporting, UTC by default.
+    #[serde(default = "Config::default_tax_timezone")]
+    pub tax_timezone: Tz,
+    /// List of entries to parse.
+    pub entries: Vec<SourceEntry>,
+}
+
+impl Config {
+    fn default_tax_timezone() -> Tz {
+        Tz::UTC
+    }
+}
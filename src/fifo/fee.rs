@@ -0,0 +1,258 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Explicit fees paid for the transactions.
+//!
+//! Fee is valued in the base currency, and split between the inventory items of the transaction:
+//! * In case of a disposal (a sale, or a swap taxed at the market value), the fee reduces the proceeds.
+//! * Otherwise, the fee is added to the cost basis of the acquired (or transferred) assets.
+//!
+//! Fiat fees are taken at their face value.
+//! Crypto fees are paid before the transaction, by consuming lots of the account making it.
+//! They are valued at the cost basis of the consumed lots, which are recorded as fee items.
+//! Fee items have no proceeds, so paying a fee isn't a disposal on its own.
+//!
+//! The UK share pooling (see `crate::uk`) books crypto fees as disposals at zero proceeds instead,
+//! since HMRC treats the tokens spent on a fee as disposed of, and their cost is only known once the disposal is matched.
+//! Both treatments deduct the same cost in total, the UK one just does it in the year in which the fee is paid.
+
+use super::{InventoryItem, Ledger};
+use crate::types::{LedgerError, LedgerErrorKind, Transaction};
+
+use rust_decimal::Decimal;
+
+impl<'a> Ledger<'a> {
+    /// Pay the fee of the transaction, if any.
+    ///
+    /// Returns the fee in the base currency.
+    pub(super) fn pay_fee(
+        &mut self,
+        transaction: &Transaction,
+    ) -> Result<Option<Decimal>, LedgerError> {
+        let Some((token, amount)) = transaction.fee() else {
+            return Ok(None);
+        };
+        if self.settings.fiat.is_fiat(token) {
+            return Ok(Some(*amount));
+        }
+
+        let key = self.holding_key(token.clone(), transaction.account());
+        let consumed_lots = self.consume_lots(&key, *amount, transaction)?;
+
+        let fee = consumed_lots
+            .iter()
+            .map(|lot| lot.amount * lot.cost_basis)
+            .sum();
+        let fee_items: Vec<_> = consumed_lots
+            .into_iter()
+            .map(|lot| InventoryItem {
+                lot_id: self.next_lot_id(),
+                ordinal: transaction.ordinal(),
+                date: transaction.date(),
+                acquisition_date: lot.held_since,
+                input_type: token.clone(),
+                input_amount: lot.amount,
+                output_type: token.clone(),
+                output_amount: Decimal::ZERO,
+                remaining_amount: Decimal::ZERO,
+                cost_basis: lot.cost_basis,
+                input_cost_basis: None,
+                sale_price: None,
+                holding_term: None,
+                disallowed_loss: None,
+                wash_sale_adjustment: None,
+                replaced_amount: Decimal::ZERO,
+                holding_period_start: None,
+                fee: None,
+                parent_lot: Some(lot.lot_id),
+                parent_tx: Some(lot.ordinal),
+                is_interest: false,
+                is_transfer: false,
+                is_fee: true,
+                account: transaction.account().map(str::to_string),
+            })
+            .collect();
+        self.ledger.entry(key).or_default().extend(fee_items);
+
+        Ok(Some(fee))
+    }
+}
+
+/// Split the fee between the items of the transaction, proportionally to their output amount.
+/// In case of a sale without any proceeds, the fee is split proportionally to the input amount instead.
+///
+/// Fee which isn't a part of a sale is added to the cost basis of the acquired assets,
+/// so it's an error if the transaction doesn't acquire any.
+///
+/// # Arguments
+/// * `fee` - Fee of the transaction, in the base currency.
+/// * `items` - Items of the transaction, not added to the ledger yet.
+/// * `is_sale` - Whether the fee reduces the proceeds, instead of being added to the cost basis.
+/// * `transaction` - Transaction paying the fee.
+pub(super) fn split_fee(
+    fee: Decimal,
+    items: &mut [InventoryItem],
+    is_sale: bool,
+    transaction: &Transaction,
+) -> Result<(), LedgerError> {
+    let by_output = !is_sale || items.iter().any(|item| !item.output_amount.is_zero());
+    let weight = |item: &InventoryItem| {
+        if by_output {
+            item.output_amount
+        } else {
+            item.input_amount
+        }
+    };
+
+    // Items without output (e.g. transfer fees) don't receive any part of the fee.
+    let total_weight: Decimal = items.iter().map(weight).sum();
+    let Some(last_idx) = items.iter().rposition(|item| !weight(item).is_zero()) else {
+        if is_sale || fee.is_zero() {
+            return Ok(());
+        }
+        return Err(LedgerError {
+            ordinal: transaction.ordinal(),
+            location: transaction.location().clone(),
+            kind: LedgerErrorKind::UnattributedFee { fee },
+        });
+    };
+    let mut remaining_fee = fee;
+
+    for (idx, item) in items.iter_mut().enumerate() {
+        if weight(item).is_zero() {
+            continue;
+        }
+
+        // The last item takes the entire remaining fee, so nothing is lost to the rounding.
+        let share = if idx == last_idx {
+            remaining_fee
+        } else {
+            fee * weight(item) / total_weight
+        };
+        remaining_fee -= share;
+
+        if !is_sale {
+            item.cost_basis += share / item.output_amount;
+        }
+        item.fee = Some(share);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fifo::LedgerSettings,
+        test_utils::{asset, dec, ledger, tx},
+        types::TransactionType::{Buying, Selling, Transfer},
+    };
+
+    #[test]
+    fn fiat_fee_reduces_sale_proceeds() {
+        let ledger = ledger(
+            vec![
+                tx(1, "2024-01-01", Buying, ("EUR", "100"), ("BTC", "1")),
+                tx(2, "2024-02-01", Selling, ("BTC", "1"), ("EUR", "150"))
+                    .new_with_fee(Some((asset("EUR"), dec("5")))),
+            ],
+            LedgerSettings::default(),
+        );
+
+        let sale = ledger.in_order()[1];
+        assert_eq!(sale.fee(), Some(dec("5")));
+        assert_eq!(sale.cost_basis(), dec("100"));
+        assert_eq!(sale.income(), Some(dec("145")));
+        assert_eq!(sale.profit(), Some(dec("45")));
+    }
+
+    #[test]
+    fn crypto_fee_is_added_to_the_cost_basis_of_the_purchase() {
+        let ledger = ledger(
+            vec![
+                tx(1, "2024-01-01", Buying, ("EUR", "200"), ("BTC", "2")),
+                tx(2, "2024-02-01", Buying, ("EUR", "100"), ("ETH", "10"))
+                    .new_with_fee(Some((asset("BTC"), dec("0.1")))),
+            ],
+            LedgerSettings::default(),
+        );
+        let items = ledger.in_order();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].remaining_amount(), dec("1.9"));
+
+        // Fee is paid with the consumed lot, valued at its cost basis.
+        let purchase = items[1];
+        assert_eq!(purchase.fee(), Some(dec("10")));
+        assert_eq!(purchase.cost_basis(), dec("11"));
+
+        let fee = items[2];
+        assert!(fee.is_fee());
+        assert_eq!(fee.input(), (&asset("BTC"), dec("0.1")));
+        assert_eq!(fee.cost_basis(), dec("100"));
+        // Paying the fee isn't a disposal on its own.
+        assert_eq!(fee.expense(), None);
+    }
+
+    #[test]
+    fn transfer_fee_goes_to_the_transferred_lots_only() {
+        let ledger = ledger(
+            vec![
+                tx(1, "2024-01-01", Buying, ("EUR", "200"), ("BTC", "2")),
+                tx(2, "2024-02-01", Transfer, ("BTC", "2"), ("BTC", "1.9"))
+                    .new_with_fee(Some((asset("EUR"), dec("3.8")))),
+            ],
+            LedgerSettings::default(),
+        );
+        let items = ledger.in_order();
+        assert_eq!(items.len(), 3);
+
+        let transferred = items[1];
+        assert_eq!(transferred.fee(), Some(dec("3.8")));
+        assert_eq!(transferred.cost_basis(), dec("102"));
+
+        // Amount lost in the transfer has no output, so it doesn't receive any part of the fee.
+        let lost = items[2];
+        assert!(lost.is_fee());
+        assert_eq!(lost.fee(), None);
+        assert_eq!(lost.cost_basis(), dec("100"));
+    }
+
+    #[test]
+    fn fee_without_output_is_an_error() {
+        let ledger = ledger(
+            vec![
+                tx(1, "2024-01-01", Buying, ("EUR", "100"), ("BTC", "1")),
+                tx(2, "2024-02-01", Transfer, ("BTC", "1"), ("BTC", "0.5")),
+            ],
+            LedgerSettings::default(),
+        );
+        let lost = ledger.in_order()[2].clone();
+        assert!(lost.output().1.is_zero());
+        let transaction = &ledger.transactions()[1];
+
+        // Sale without proceeds splits the fee by the sold amount.
+        let mut items = [lost.clone(), lost.clone()];
+        split_fee(dec("3"), &mut items, true, transaction).unwrap();
+        assert_eq!(items[0].fee(), Some(dec("1.5")));
+        assert_eq!(items[1].fee(), Some(dec("1.5")));
+        assert_eq!(items[0].cost_basis(), dec("100"));
+
+        // Otherwise, the fee can't be added to the cost basis of any asset.
+        let error = split_fee(dec("3"), &mut [lost], false, transaction).unwrap_err();
+        assert_eq!(error.ordinal, 2);
+        assert_eq!(
+            error.kind,
+            LedgerErrorKind::UnattributedFee { fee: dec("3") }
+        );
+    }
+}
@@ -0,0 +1,221 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Yearly report according to the German income tax law (EStG).
+//!
+//! * Disposals of assets held for more than one year are tax-free.
+//!   Gains & losses of the remaining disposals are private sales (§23 EStG).
+//! * Interest & staking rewards are other income (§22 Nr. 3 EStG).
+//!
+//! Both are subject to a _Freigrenze_, i.e. the entire amount is taxable once it reaches the threshold.
+
+use super::{HoldingTerm, Ledger};
+
+use chrono::Datelike;
+use itertools::Itertools;
+use rust_decimal::Decimal;
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+};
+
+/// Yearly report of the private sales & other income, per German tax law.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct GermanYearlyReport {
+    /// Calendar year of the report.
+    pub year: i32,
+    /// Net gain (or loss, if negative) of the disposals held for one year or less (§23 EStG).
+    pub private_sales_gain: Decimal,
+    /// Net gain (or loss, if negative) of the disposals held for more than one year.
+    pub tax_free_gain: Decimal,
+    /// Income from interest & staking rewards (§22 Nr. 3 EStG).
+    pub other_income: Decimal,
+}
+
+impl GermanYearlyReport {
+    /// Freigrenze of the other income (§22 Nr. 3 EStG).
+    pub const OTHER_INCOME_LIMIT: Decimal = Decimal::from_parts(256, 0, 0, false, 0);
+
+    /// Freigrenze of the private sales (§23 Abs. 3 EStG), 600 EUR until 2023 & 1000 EUR since 2024.
+    pub fn private_sales_limit(&self) -> Decimal {
+        if self.year <= 2023 {
+            Decimal::from(600)
+        } else {
+            Decimal::from(1000)
+        }
+    }
+
+    /// Whether the private sales gain reaches the Freigrenze, making all of it taxable.
+    pub fn is_private_sales_taxable(&self) -> bool {
+        self.private_sales_gain >= self.private_sales_limit()
+    }
+
+    /// Whether the other income reaches the Freigrenze, making all of it taxable.
+    pub fn is_other_income_taxable(&self) -> bool {
+        self.other_income >= Self::OTHER_INCOME_LIMIT
+    }
+}
+
+impl Display for GermanYearlyReport {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let taxable = |is_taxable: bool| if is_taxable { "taxable" } else { "tax-free" };
+
+        write!(
+            f,
+            "Year {}: §23 Private Sales Gain: {:.2} (Freigrenze {:.2}, {}), Tax-Free Gain: {:.2}, §22 Other Income: {:.2} (Freigrenze {:.2}, {})",
+            self.year,
+            self.private_sales_gain,
+            self.private_sales_limit(),
+            taxable(self.is_private_sales_taxable()),
+            self.tax_free_gain,
+            self.other_income,
+            Self::OTHER_INCOME_LIMIT,
+            taxable(self.is_other_income_taxable()),
+        )
+    }
+}
+
+impl<'a> Ledger<'a> {
+    /// Yearly reports per German tax law, sorted by year.
+    ///
+    /// Disposals are classified by their holding term, i.e. by the German holding period rule
+    /// if the ledger uses the German tax profile.
+    /// Swaps are only part of the private sales if they're taxed at the market value.
+    pub fn german_tax_report(&'a self) -> Vec<GermanYearlyReport> {
+        let mut total_report = HashMap::<i32, GermanYearlyReport>::new();
+
+        for item in self.in_order() {
+            let year = item.date.year();
+            let report = total_report
+                .entry(year)
+                .or_insert_with(|| GermanYearlyReport {
+                    year,
+                    private_sales_gain: Decimal::ZERO,
+                    tax_free_gain: Decimal::ZERO,
+                    other_income: Decimal::ZERO,
+                });
+
+            if let Some(profit) = item.profit() {
+                match item.holding_term {
+                    Some(HoldingTerm::Exempt) => report.tax_free_gain += profit,
+                    _ => report.private_sales_gain += profit,
+                }
+            }
+
+            if item.is_interest {
+                report.other_income += item.input_amount;
+            }
+        }
+
+        total_report
+            .into_values()
+            .sorted_by_key(|report| report.year)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fifo::{LedgerSettings, SwapTreatment, TaxProfile},
+        test_utils::{asset, dec, tx},
+        types::TransactionType::{Buying, Interest, Selling},
+    };
+
+    fn report(year: i32, private_sales_gain: &str, other_income: &str) -> GermanYearlyReport {
+        GermanYearlyReport {
+            year,
+            private_sales_gain: dec(private_sales_gain),
+            tax_free_gain: Decimal::ZERO,
+            other_income: dec(other_income),
+        }
+    }
+
+    #[test]
+    fn disposals_after_one_year_are_tax_free() {
+        let settings = LedgerSettings {
+            tax_profile: Some(TaxProfile::Germany),
+            swap_treatment: SwapTreatment::MarketValue,
+            ..Default::default()
+        };
+        let transactions = vec![
+            tx(1, "2023-01-15", Buying, ("EUR", "200"), ("BTC", "2")),
+            tx(2, "2023-03-01", Interest, ("EUR", "30"), ("BTC", "0.1")),
+            // Held for exactly one year.
+            tx(3, "2024-01-15", Selling, ("BTC", "1"), ("EUR", "150")),
+            // Held for more than one year.
+            tx(4, "2024-01-16", Selling, ("BTC", "1"), ("EUR", "180")),
+        ];
+        let ledger = Ledger::new(transactions, settings, None).unwrap();
+
+        assert_eq!(
+            ledger.german_tax_report(),
+            vec![
+                GermanYearlyReport {
+                    year: 2023,
+                    private_sales_gain: Decimal::ZERO,
+                    tax_free_gain: Decimal::ZERO,
+                    other_income: dec("30"),
+                },
+                GermanYearlyReport {
+                    year: 2024,
+                    private_sales_gain: dec("50"),
+                    tax_free_gain: dec("80"),
+                    other_income: Decimal::ZERO,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn private_sales_freigrenze() {
+        assert!(!report(2023, "599.99", "0").is_private_sales_taxable());
+        assert!(report(2023, "600", "0").is_private_sales_taxable());
+        assert!(!report(2024, "999.99", "0").is_private_sales_taxable());
+        assert!(report(2024, "1000", "0").is_private_sales_taxable());
+        assert!(!report(2024, "-2000", "0").is_private_sales_taxable());
+    }
+
+    #[test]
+    fn other_income_freigrenze() {
+        assert!(!report(2024, "0", "255.99").is_other_income_taxable());
+        assert!(report(2024, "0", "256").is_other_income_taxable());
+    }
+
+    #[test]
+    fn german_profile_requires_market_value_swaps() {
+        let mut settings = LedgerSettings {
+            tax_profile: Some(TaxProfile::Germany),
+            ..Default::default()
+        };
+        assert!(settings.validate().is_err());
+
+        settings.swap_treatment = SwapTreatment::MarketValue;
+        assert_eq!(settings.validate(), Ok(()));
+    }
+
+    #[test]
+    fn german_profile_requires_eur_base_currency() {
+        let mut settings = LedgerSettings {
+            tax_profile: Some(TaxProfile::Germany),
+            swap_treatment: SwapTreatment::MarketValue,
+            ..Default::default()
+        };
+        settings.fiat.currencies.push(asset("USD"));
+        settings.fiat.base_currency = asset("USD");
+
+        let error = settings.validate().unwrap_err();
+        assert!(error.contains("EUR"), "{error}");
+        assert!(error.contains("USD"), "{error}");
+    }
+}
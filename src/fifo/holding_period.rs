@@ -0,0 +1,181 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Classification of the disposals by the holding period of the disposed asset.
+//!
+//! Many jurisdictions tax the gains differently, depending on how long the asset was held.
+//! E.g. the US splits short-term & long-term gains at one year, while Germany exempts
+//! private crypto sales after one year.
+
+use chrono::{Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+
+/// Settings of the holding period classification.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+pub struct HoldingPeriodSettings {
+    /// Number of months the asset must be held for, before its disposal is long-term.
+    /// Disposal is long-term if it happens **after** the threshold elapsed.
+    #[serde(default = "HoldingPeriodSettings::default_long_term_months")]
+    pub long_term_months: u32,
+    /// Whether the long-term gains are exempt from tax.
+    #[serde(default)]
+    pub long_term_exempt: bool,
+}
+
+impl HoldingPeriodSettings {
+    fn default_long_term_months() -> u32 {
+        12
+    }
+
+    /// Classify the disposal of the asset held since `acquired`, and disposed of on `disposed`.
+    pub fn classify(&self, acquired: NaiveDate, disposed: NaiveDate) -> HoldingTerm {
+        let long_term_from = acquired
+            .checked_add_months(Months::new(self.long_term_months))
+            .unwrap_or(NaiveDate::MAX);
+
+        match (disposed > long_term_from, self.long_term_exempt) {
+            (false, _) => HoldingTerm::ShortTerm,
+            (true, false) => HoldingTerm::LongTerm,
+            (true, true) => HoldingTerm::Exempt,
+        }
+    }
+}
+
+impl Default for HoldingPeriodSettings {
+    fn default() -> Self {
+        HoldingPeriodSettings {
+            long_term_months: Self::default_long_term_months(),
+            long_term_exempt: false,
+        }
+    }
+}
+
+/// Holding period class of a disposal.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize)]
+pub enum HoldingTerm {
+    /// Asset was held for less than (or exactly) the threshold.
+    ShortTerm,
+    /// Asset was held for longer than the threshold.
+    LongTerm,
+    /// Asset was held for longer than the threshold, and the long-term gains are exempt.
+    Exempt,
+}
+
+impl Display for HoldingTerm {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            HoldingTerm::ShortTerm => write!(f, "Short-Term"),
+            HoldingTerm::LongTerm => write!(f, "Long-Term"),
+            HoldingTerm::Exempt => write!(f, "Exempt"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fifo::{HoldingPeriodGains, Ledger, LedgerSettings},
+        test_utils::{date, dec, tx},
+        types::TransactionType::{Buying, Selling, Transfer},
+    };
+
+    #[test]
+    fn disposal_is_long_term_only_after_the_threshold() {
+        let settings = HoldingPeriodSettings::default();
+        let acquired = date("2023-03-15");
+
+        assert_eq!(
+            settings.classify(acquired, date("2023-03-15")),
+            HoldingTerm::ShortTerm
+        );
+        assert_eq!(
+            settings.classify(acquired, date("2024-03-15")),
+            HoldingTerm::ShortTerm
+        );
+        assert_eq!(
+            settings.classify(acquired, date("2024-03-16")),
+            HoldingTerm::LongTerm
+        );
+
+        // Threshold of a leap day acquisition ends on the last day of February.
+        assert_eq!(
+            settings.classify(date("2024-02-29"), date("2025-02-28")),
+            HoldingTerm::ShortTerm
+        );
+        assert_eq!(
+            settings.classify(date("2024-02-29"), date("2025-03-01")),
+            HoldingTerm::LongTerm
+        );
+    }
+
+    #[test]
+    fn long_term_gains_can_be_exempt() {
+        let settings = HoldingPeriodSettings {
+            long_term_months: 6,
+            long_term_exempt: true,
+        };
+
+        assert_eq!(
+            settings.classify(date("2024-01-31"), date("2024-07-31")),
+            HoldingTerm::ShortTerm
+        );
+        assert_eq!(
+            settings.classify(date("2024-01-31"), date("2024-08-01")),
+            HoldingTerm::Exempt
+        );
+    }
+
+    #[test]
+    fn yearly_report_splits_gains_by_holding_term() {
+        let settings = LedgerSettings {
+            holding_period: Some(HoldingPeriodSettings::default()),
+            ..Default::default()
+        };
+        let transactions = vec![
+            tx(1, "2023-01-10", Buying, ("EUR", "100"), ("BTC", "1")),
+            tx(2, "2023-06-01", Buying, ("EUR", "300"), ("BTC", "1")),
+            // Transfer keeps the holding period of the transferred lots.
+            tx(3, "2023-12-01", Transfer, ("BTC", "2"), ("BTC", "2")),
+            // Sells the first lot after a year, and a half of the second one before.
+            tx(4, "2024-03-01", Selling, ("BTC", "1.5"), ("EUR", "450")),
+        ];
+        let ledger = Ledger::new(transactions, settings, None).unwrap();
+
+        let terms: Vec<_> = ledger
+            .in_order()
+            .iter()
+            .filter(|item| item.ordinal() == 4)
+            .map(|item| (item.holding_term(), item.profit()))
+            .collect();
+        assert_eq!(
+            terms,
+            vec![
+                (Some(HoldingTerm::LongTerm), Some(dec("200"))),
+                (Some(HoldingTerm::ShortTerm), Some(dec("0"))),
+            ]
+        );
+
+        let reports = ledger.yearly_reports();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].holding_period_gains, Some(Default::default()));
+        assert_eq!(
+            reports[1].holding_period_gains,
+            Some(HoldingPeriodGains {
+                short_term: dec("0"),
+                long_term: dec("200"),
+                exempt: dec("0"),
+            })
+        );
+    }
+}
@@ -0,0 +1,181 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lineage of the lots, i.e. from which lot each inventory item was carved.
+//!
+//! Every inventory item is a lot with its own Id, assigned in the order the items are created.
+//! Items created by consuming another lot (swaps, sales, transfers & fees) reference it as their parent,
+//! so the ancestry of any item can be followed back to the original acquisition with fiat.
+
+use super::{InventoryItem, Ledger};
+
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+};
+
+/// Unique Id of the lot (inventory item) in the ledger.
+///
+/// Ids are assigned sequentially, starting from 1, so they're stable for the same input.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize)]
+pub struct LotId(u32);
+
+impl LotId {
+    /// Id of the first lot in the ledger.
+    pub(super) const FIRST: LotId = LotId(1);
+
+    /// Id following this one.
+    pub(super) fn next(self) -> LotId {
+        LotId(self.0.checked_add(1).expect("Mustn't overflow"))
+    }
+}
+
+impl Display for LotId {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl<'a> Ledger<'a> {
+    /// Inventory item with the given lot Id, if it exists.
+    pub fn lot(&'a self, lot_id: LotId) -> Option<&'a InventoryItem> {
+        self.by_lot_id
+            .get_or_init(|| {
+                self.ledger
+                    .values()
+                    .flat_map(|items| items.iter())
+                    .map(|item| (item.lot_id, item))
+                    .collect::<HashMap<_, _>>()
+            })
+            .get(&lot_id)
+            .copied()
+    }
+
+    /// Ancestry of the item, i.e. the lots from which it was carved.
+    ///
+    /// Starts with the direct parent & ends with the original acquisition (e.g. buying with fiat).
+    /// Empty if the item is an acquisition itself.
+    pub fn ancestry(&'a self, item: &InventoryItem) -> Vec<&'a InventoryItem> {
+        std::iter::successors(
+            item.parent_lot.and_then(|parent| self.lot(parent)),
+            |parent| {
+                parent
+                    .parent_lot
+                    .and_then(|grandparent| self.lot(grandparent))
+            },
+        )
+        .collect()
+    }
+
+    /// Allocate the Id of the next created lot.
+    pub(super) fn next_lot_id(&mut self) -> LotId {
+        let lot_id = self.next_lot_id;
+        self.next_lot_id = lot_id.next();
+        lot_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fifo::LedgerSettings,
+        test_utils::{asset, dec, tx},
+        types::{
+            Transaction,
+            TransactionType::{Buying, Selling, Swap},
+        },
+    };
+
+    fn transactions() -> Vec<Transaction> {
+        vec![
+            tx(1, "2024-01-01", Buying, ("EUR", "100"), ("BTC", "1")),
+            tx(2, "2024-01-02", Buying, ("EUR", "300"), ("BTC", "1")),
+            tx(3, "2024-01-03", Swap, ("BTC", "1.5"), ("ETH", "30")),
+            tx(4, "2024-01-04", Selling, ("ETH", "25"), ("EUR", "500"))
+                .new_with_fee(Some((asset("BTC"), dec("0.1")))),
+        ]
+    }
+
+    /// Lot Id, parent lot & parent transaction of each item, in order.
+    fn lineage<'a>(ledger: &'a Ledger<'a>) -> Vec<(u32, LotId, Option<LotId>, Option<u32>)> {
+        ledger
+            .in_order()
+            .iter()
+            .map(|item| {
+                (
+                    item.ordinal(),
+                    item.lot_id(),
+                    item.parent_lot(),
+                    item.parent_tx(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn consumed_lots_are_referenced_as_parents() {
+        let ledger = Ledger::new(transactions(), LedgerSettings::default(), None).unwrap();
+        let lineage = lineage(&ledger);
+
+        // Ids are unique, and the swap consumes both purchases.
+        let mut ids: Vec<_> = lineage.iter().map(|(_, lot_id, _, _)| *lot_id).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), lineage.len());
+
+        let children_of = |parent: LotId| -> Vec<u32> {
+            lineage
+                .iter()
+                .filter(|(_, _, parent_lot, _)| *parent_lot == Some(parent))
+                .map(|(ordinal, _, _, _)| *ordinal)
+                .collect()
+        };
+        let (first_buy, second_buy) = (lineage[0].1, lineage[1].1);
+        assert_eq!(lineage[0].2, None);
+        assert_eq!(lineage[1].2, None);
+        assert_eq!(children_of(first_buy), vec![3]);
+        // Second purchase is swapped in part, and pays the fee of the sale.
+        assert_eq!(children_of(second_buy), vec![3, 4]);
+
+        // Sale is carved from the swap fragment of the first purchase first.
+        let sale = ledger
+            .in_order()
+            .iter()
+            .find(|item| item.ordinal() == 4 && !item.is_fee())
+            .unwrap();
+        assert_eq!(sale.parent_tx(), Some(3));
+        let ancestry: Vec<_> = ledger
+            .ancestry(sale)
+            .iter()
+            .map(|item| (item.ordinal(), item.output()))
+            .collect();
+        assert_eq!(
+            ancestry,
+            vec![
+                (3, (&asset("ETH"), dec("20"))),
+                (1, (&asset("BTC"), dec("1"))),
+            ]
+        );
+    }
+
+    #[test]
+    fn lot_ids_are_stable() {
+        let first = Ledger::new(transactions(), LedgerSettings::default(), None).unwrap();
+        let second = Ledger::new(transactions(), LedgerSettings::default(), None).unwrap();
+
+        assert_eq!(lineage(&first), lineage(&second));
+        assert_eq!(first.in_order()[0].lot_id(), LotId::FIRST);
+        assert!(first.lot(LotId(100)).is_none());
+    }
+}
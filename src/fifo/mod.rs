@@ -37,12 +37,55 @@
 //! 2. 30 units, remaining for future swaps.
 //!
 //! The input amount of the original transaction & the output amount of the swap are fragmented in the same way.
+//!
+//! # Cost Basis Methods
+//!
+//! The order in which the lots are consumed in step 1 is determined by the [`CostBasisMethod`].
+//! FIFO is the default, but LIFO, HIFO and weighted average cost are supported as well.
+//! All methods fragment the consumed lots in the same way.
+//!
+//! # Swaps
+//!
+//! By default, swaps aren't taxable and the acquired asset carries over the cost basis of the swapped asset.
+//! With the [`SwapTreatment::MarketValue`], swaps are disposals at the market value, same as the sales.
+//!
+//! # Lineage
+//!
+//! Each fragment is a lot with its own [`LotId`], referencing the lot it was carved from.
+//! The chain of the parent lots can be followed back to the original acquisition via [`Ledger::ancestry`].
+
+mod fee;
+mod germany;
+mod holding_period;
+mod lineage;
+mod open_lots;
+mod strategy;
+mod swap_treatment;
+mod tax_profile;
+mod unrealized;
+mod wash_sale;
+
+pub use germany::GermanYearlyReport;
+pub use holding_period::{HoldingPeriodSettings, HoldingTerm};
+pub use lineage::LotId;
+pub use open_lots::{open_lots_totals, OpenLot, OpenLotsTotal};
+pub use strategy::CostBasisMethod;
+pub use swap_treatment::SwapTreatment;
+pub use tax_profile::TaxProfile;
+pub use unrealized::{unrealized_totals, UnrealizedLot, UnrealizedTotal};
+
+use crate::{
+    prices::PriceTable,
+    types::{
+        AssetType, CsvLineData, FiatSettings, FiscalYear, HoldingKey, LedgerError, LedgerErrorKind,
+        Transaction, TransactionType,
+    },
+};
 
-use crate::types::{AssetType, CsvLineData, Transaction, TransactionType};
-
-use chrono::{Datelike, NaiveDate};
+use chrono::NaiveDate;
 use itertools::Itertools;
 use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 use std::{
     borrow::Cow,
     cell::OnceCell,
@@ -51,8 +94,10 @@ use std::{
 };
 
 /// Inventory item for the FIFO asset management system.
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
 pub struct InventoryItem {
+    /// Unique Id of the lot.
+    lot_id: LotId,
     /// Ordinal number of the transaction in the ledger.
     ordinal: u32,
     /// Date on which the transaction was made.
@@ -72,39 +117,167 @@ pub struct InventoryItem {
     remaining_amount: Decimal,
     /// Cost basis of the asset, i.e. the price at which it was acquired.
     cost_basis: Decimal,
+    /// Unit cost basis of the consumed input, if it differs from the cost basis of the output.
+    /// E.g. for swaps taxed at the market value.
+    input_cost_basis: Option<Decimal>,
     /// Unit sale price of the asset, if it was sold.
     sale_price: Option<Decimal>,
-    /// Parent transaction Id, if this item uses assets from another transaction.
-    parent_tx: Option<usize>,
+    /// Holding period class of the disposal, if it's a disposal & classification is configured.
+    holding_term: Option<HoldingTerm>,
+    /// Loss disallowed by the wash sale rule, if the item is an affected loss sale.
+    disallowed_loss: Option<Decimal>,
+    /// Disallowed loss added to the cost basis, if the item is a wash sale replacement lot.
+    wash_sale_adjustment: Option<Decimal>,
+    /// Amount of the lot already used as a wash sale replacement.
+    replaced_amount: Decimal,
+    /// Start of the holding period, if the holding period of the lot sold at a loss is tacked on (wash sale replacement).
+    holding_period_start: Option<NaiveDate>,
+    /// Part of the transaction fee attributed to the item, in the base currency.
+    fee: Option<Decimal>,
+    /// Id of the lot consumed to create this item, if it uses assets from another lot.
+    parent_lot: Option<LotId>,
+    /// Ordinal of the transaction which created the parent lot.
+    parent_tx: Option<u32>,
     /// Whether the asset was acquired via interest.
     is_interest: bool,
+    /// Whether the item was created by a transfer between own wallets or exchanges.
+    is_transfer: bool,
+    /// Whether the item records the assets consumed to pay the transaction fee.
+    is_fee: bool,
+    /// Account (wallet or exchange) holding the output asset, if known.
+    account: Option<String>,
 }
 
 impl InventoryItem {
+    /// Unique Id of the lot.
+    pub fn lot_id(&self) -> LotId {
+        self.lot_id
+    }
+
+    /// Id of the lot consumed to create this item, `None` for the acquisitions (e.g. buying with fiat).
+    pub fn parent_lot(&self) -> Option<LotId> {
+        self.parent_lot
+    }
+
+    /// Ordinal of the transaction which created the parent lot, `None` for the acquisitions.
+    pub fn parent_tx(&self) -> Option<u32> {
+        self.parent_tx
+    }
+
+    /// Ordinal number of the transaction which created the item.
+    pub fn ordinal(&self) -> u32 {
+        self.ordinal
+    }
+
+    /// Date on which the transaction was made.
+    pub fn date(&self) -> NaiveDate {
+        self.date
+    }
+
+    /// Date on which the origin asset was acquired.
+    pub fn acquisition_date(&self) -> NaiveDate {
+        self.acquisition_date
+    }
+
+    /// Input asset and the consumed input amount.
+    pub fn input(&self) -> (&AssetType, Decimal) {
+        (&self.input_type, self.input_amount)
+    }
+
+    /// Output asset and the output amount.
+    pub fn output(&self) -> (&AssetType, Decimal) {
+        (&self.output_type, self.output_amount)
+    }
+
+    /// Output amount which wasn't consumed yet.
+    pub fn remaining_amount(&self) -> Decimal {
+        self.remaining_amount
+    }
+
+    /// Unit sale price of the asset, if it was sold.
+    pub fn sale_price(&self) -> Option<Decimal> {
+        self.sale_price
+    }
+
+    /// Holding period class of the disposal, if it's a disposal & classification is configured.
+    pub fn holding_term(&self) -> Option<HoldingTerm> {
+        self.holding_term
+    }
+
+    /// Loss disallowed by the wash sale rule, if the item is an affected loss sale.
+    pub fn disallowed_loss(&self) -> Option<Decimal> {
+        self.disallowed_loss
+    }
+
+    /// Disallowed loss added to the cost basis, if the item is a wash sale replacement lot.
+    pub fn wash_sale_adjustment(&self) -> Option<Decimal> {
+        self.wash_sale_adjustment
+    }
+
+    /// Part of the transaction fee attributed to the item, in the base currency.
+    ///
+    /// Fee of a sale is deducted from the income, otherwise it's already included in the cost basis.
+    pub fn fee(&self) -> Option<Decimal> {
+        self.fee
+    }
+
+    /// Whether the asset was acquired via interest.
+    pub fn is_interest(&self) -> bool {
+        self.is_interest
+    }
+
+    /// Whether the item records the assets consumed to pay the transaction fee.
+    pub fn is_fee(&self) -> bool {
+        self.is_fee
+    }
+
+    /// Account (wallet or exchange) holding the output asset, if known.
+    pub fn account(&self) -> Option<&str> {
+        self.account.as_deref()
+    }
+
     /// Cost basis of the asset.
     pub fn cost_basis(&self) -> Decimal {
         self.cost_basis
     }
 
+    /// Date since which the asset is held.
+    ///
+    /// Transfers keep the acquisition date of the transferred lot,
+    /// while all other transactions start a new holding period, unless it's tacked on by the wash sale rule.
+    fn held_since(&self) -> NaiveDate {
+        if let Some(start) = self.holding_period_start {
+            start
+        } else if self.is_transfer {
+            self.acquisition_date
+        } else {
+            self.date
+        }
+    }
+
     /// Income of the transaction.
-    /// Equals the amount received in fiat (EUR).
+    /// Equals the amount received in fiat (base currency), reduced by the fee.
     pub fn income(&self) -> Option<Decimal> {
         self.sale_price
-            .map(|sale_price| sale_price * self.input_amount)
+            .map(|sale_price| sale_price * self.input_amount - self.fee.unwrap_or_default())
     }
 
     /// Expanse of the transaction.
     /// Equals the outflow of the value tied to the asset.
     pub fn expense(&self) -> Option<Decimal> {
         self.sale_price
-            .map(|_sale_price| self.input_amount * self.cost_basis)
+            .map(|_sale_price| self.input_amount * self.input_cost_basis.unwrap_or(self.cost_basis))
     }
 
     /// Profit of the transaction.
     /// If the asset was not sold yet, return `None`.
+    ///
+    /// Loss disallowed by the wash sale rule isn't included.
     pub fn profit(&self) -> Option<Decimal> {
         match (self.income(), self.expense()) {
-            (Some(income), Some(expense)) => Some(income - expense),
+            (Some(income), Some(expense)) => {
+                Some(income - expense + self.disallowed_loss.unwrap_or_default())
+            }
             _ => None,
         }
     }
@@ -128,71 +301,122 @@ impl InventoryItem {
             income_amount: Option<String>,
             expense_amount: Option<String>,
             profit: Option<String>,
+            fee: Option<String>,
+            holding_period: Option<String>,
+            wash_sale_adjustment: Option<String>,
+            account: Option<String>,
+            lot_id: String,
+            parent_lot: Option<String>,
         }
 
         impl CsvLineData for CsvLine {
-            fn ordinal(&self) -> Cow<str> {
+            fn ordinal(&self) -> Cow<'_, str> {
                 Cow::Borrowed(&self.ordinal)
             }
 
-            fn transaction_date(&self) -> Cow<str> {
+            fn transaction_date(&self) -> Cow<'_, str> {
                 Cow::Borrowed(&self.transaction_date)
             }
 
-            fn acquisition_date(&self) -> Cow<str> {
+            fn acquisition_date(&self) -> Cow<'_, str> {
                 Cow::Borrowed(&self.acquisition_date)
             }
 
-            fn action(&self) -> Cow<str> {
+            fn action(&self) -> Cow<'_, str> {
                 Cow::Borrowed(&self.action)
             }
 
-            fn input_type(&self) -> Cow<str> {
+            fn input_type(&self) -> Cow<'_, str> {
                 Cow::Borrowed(&self.input_type)
             }
 
-            fn input_amount(&self) -> Cow<str> {
+            fn input_amount(&self) -> Cow<'_, str> {
                 Cow::Borrowed(&self.input_amount)
             }
 
-            fn output_type(&self) -> Cow<str> {
+            fn output_type(&self) -> Cow<'_, str> {
                 Cow::Borrowed(&self.output_type)
             }
 
-            fn output_amount(&self) -> Cow<str> {
+            fn output_amount(&self) -> Cow<'_, str> {
                 Cow::Borrowed(&self.output_amount)
             }
 
-            fn income_amount(&self) -> Option<Cow<str>> {
+            fn income_amount(&self) -> Option<Cow<'_, str>> {
                 self.income_amount.as_deref().map(Cow::Borrowed)
             }
 
-            fn expense_amount(&self) -> Option<Cow<str>> {
+            fn expense_amount(&self) -> Option<Cow<'_, str>> {
                 self.expense_amount.as_deref().map(Cow::Borrowed)
             }
 
-            fn profit(&self) -> Option<Cow<str>> {
+            fn profit(&self) -> Option<Cow<'_, str>> {
                 self.profit.as_deref().map(Cow::Borrowed)
             }
+
+            fn fee(&self) -> Option<Cow<'_, str>> {
+                self.fee.as_deref().map(Cow::Borrowed)
+            }
+
+            fn holding_period(&self) -> Option<Cow<'_, str>> {
+                self.holding_period.as_deref().map(Cow::Borrowed)
+            }
+
+            fn wash_sale_adjustment(&self) -> Option<Cow<'_, str>> {
+                self.wash_sale_adjustment.as_deref().map(Cow::Borrowed)
+            }
+
+            fn account(&self) -> Option<Cow<'_, str>> {
+                self.account.as_deref().map(Cow::Borrowed)
+            }
+
+            fn lot_id(&self) -> Option<Cow<'_, str>> {
+                Some(Cow::Borrowed(&self.lot_id))
+            }
+
+            fn parent_lot(&self) -> Option<Cow<'_, str>> {
+                self.parent_lot.as_deref().map(Cow::Borrowed)
+            }
         }
 
         let ordinal = format!("{}", self.ordinal);
         let transaction_date = self.date.format("%d.%m.%Y").to_string();
         let acquisition_date = self.acquisition_date.format("%d.%m.%Y").to_string();
-        let action = format!("{:?}", tx.tx_type());
+        let action = if self.is_fee {
+            "Fee".to_string()
+        } else {
+            format!("{:?}", tx.tx_type())
+        };
 
-        let input_type = format!("{}", tx.input().0);
+        let input_type = format!("{}", self.input_type);
         let input_amount = format!("{}", self.input_amount);
 
-        let output_type = format!("{}", tx.output().0);
+        let output_type = format!("{}", self.output_type);
         let output_amount = format!("{}", self.output_amount);
 
+        let account = match (tx.account(), tx.receiving_account()) {
+            (Some(from), Some(to)) if from != to => Some(format!("{from} -> {to}")),
+            (from, to) => from.or(to).map(str::to_string),
+        };
+
         let income_amount = self.income().map(|income| format!("{income}"));
 
         let expense_amount = self.expense().map(|expense| format!("{expense}"));
 
         let profit = self.profit().map(|profit| format!("{profit}"));
 
+        let fee = self.fee.map(|fee| format!("{fee}"));
+
+        let holding_period = self.holding_term.map(|term| format!("{term}"));
+
+        let wash_sale_adjustment = self
+            .disallowed_loss
+            .or(self.wash_sale_adjustment)
+            .map(|adjustment| format!("{adjustment}"));
+
+        let lot_id = format!("{}", self.lot_id);
+        let parent_lot = self.parent_lot.map(|parent| format!("{parent}"));
+
         CsvLine {
             ordinal,
             transaction_date,
@@ -205,6 +429,12 @@ impl InventoryItem {
             income_amount,
             expense_amount,
             profit,
+            fee,
+            holding_period,
+            wash_sale_adjustment,
+            account,
+            lot_id,
+            parent_lot,
         }
     }
 }
@@ -213,18 +443,49 @@ impl InventoryItem {
 type Year = i32;
 
 /// Yearly income & loss report, with remaining zero-cost assets per year.
-struct YearlyReport {
-    /// Year for which the report is generated.
-    year: Year,
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct YearlyReport {
+    /// Label of the (fiscal) year for which the report is generated.
+    pub label: String,
     /// Total income incurred by selling of assets.
-    sell_income: Decimal,
+    pub sell_income: Decimal,
     /// Total income incurred by interest.
-    interest_income: Decimal,
+    pub interest_income: Decimal,
     /// Total expense incurred by selling of assets.
-    expense: Decimal,
+    pub expense: Decimal,
+    /// Total fees paid, already included in the income & the cost basis.
+    pub fees: Decimal,
+    /// Gains per holding period class, `None` if the classification isn't configured.
+    pub holding_period_gains: Option<HoldingPeriodGains>,
+    /// Total loss disallowed by the wash sale rule, `None` if the rule isn't enabled.
+    pub disallowed_loss: Option<Decimal>,
+}
+
+/// Gains of the disposals, split by the holding period class.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize)]
+pub struct HoldingPeriodGains {
+    /// Total gain (or loss) of the short-term disposals.
+    pub short_term: Decimal,
+    /// Total gain (or loss) of the long-term disposals.
+    pub long_term: Decimal,
+    /// Total gain (or loss) of the exempt long-term disposals.
+    pub exempt: Decimal,
 }
 
 impl YearlyReport {
+    /// Profit of the year, i.e. the income reduced by the expense.
+    ///
+    /// Loss disallowed by the wash sale rule isn't included.
+    pub fn profit(&self) -> Decimal {
+        self.sell_income
+            .checked_add(self.interest_income)
+            .expect("Mustn't overflow")
+            .checked_sub(self.expense)
+            .expect("Mustn't underflow")
+            .checked_add(self.disallowed_loss.unwrap_or_default())
+            .expect("Mustn't overflow")
+    }
+
     fn add_sell_income(&mut self, amount: Decimal) {
         self.sell_income = self
             .sell_income
@@ -245,22 +506,121 @@ impl YearlyReport {
             .checked_add(amount)
             .expect("Unexpected overflow.");
     }
+
+    fn add_fee(&mut self, amount: Decimal) {
+        self.fees = self.fees.checked_add(amount).expect("Unexpected overflow.");
+    }
+
+    fn add_holding_period_gain(&mut self, term: HoldingTerm, amount: Decimal) {
+        let gains = self
+            .holding_period_gains
+            .get_or_insert_with(Default::default);
+        let gain = match term {
+            HoldingTerm::ShortTerm => &mut gains.short_term,
+            HoldingTerm::LongTerm => &mut gains.long_term,
+            HoldingTerm::Exempt => &mut gains.exempt,
+        };
+        *gain = gain.checked_add(amount).expect("Unexpected overflow.");
+    }
+
+    fn add_disallowed_loss(&mut self, amount: Decimal) {
+        let disallowed_loss = self.disallowed_loss.get_or_insert(Decimal::ZERO);
+        *disallowed_loss = disallowed_loss
+            .checked_add(amount)
+            .expect("Unexpected overflow.");
+    }
 }
 
 impl Display for YearlyReport {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        let profit = self
-            .sell_income
-            .checked_add(self.interest_income)
-            .expect("Mustn't overflow")
-            .checked_sub(self.expense)
-            .expect("Mustn't underflow");
+        let profit = self.profit();
 
         write!(
             f,
-            "Year {}: Sell Income: {:.2}, Interest Income: {:.2}, Expense: {:.2}, Profit: {:.2}",
-            self.year, self.sell_income, self.interest_income, self.expense, profit,
-        )
+            "Year {}: Sell Income: {:.2}, Interest Income: {:.2}, Expense: {:.2}, Profit: {:.2}, Fees: {:.2}",
+            self.label, self.sell_income, self.interest_income, self.expense, profit, self.fees,
+        )?;
+
+        if let Some(gains) = &self.holding_period_gains {
+            write!(
+                f,
+                ", Short-Term Gain: {:.2}, Long-Term Gain: {:.2}, Exempt Gain: {:.2}",
+                gains.short_term, gains.long_term, gains.exempt,
+            )?;
+        }
+
+        if let Some(disallowed_loss) = self.disallowed_loss {
+            write!(f, ", Disallowed Wash Sale Loss: {disallowed_loss:.2}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Settings which determine how the ledger processes the transactions.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Deserialize)]
+pub struct LedgerSettings {
+    /// Method used to select the lots consumed by a disposal.
+    #[serde(default)]
+    pub cost_basis_method: CostBasisMethod,
+    /// Whether separate inventories are kept per account (wallet or exchange).
+    /// Disposals then only consume lots from the account which made them.
+    #[serde(default)]
+    pub per_account_inventory: bool,
+    /// Classification of the disposals by holding period, disabled if `None`.
+    #[serde(default)]
+    pub holding_period: Option<HoldingPeriodSettings>,
+    /// Jurisdiction specific rules, if any.
+    /// Holding period rule of the profile takes precedence over the `holding_period` settings.
+    #[serde(default)]
+    pub tax_profile: Option<TaxProfile>,
+    /// Whether losses are disallowed by the US wash sale rule.
+    #[serde(default)]
+    pub wash_sales: bool,
+    /// Treatment of the crypto-to-crypto swaps.
+    #[serde(default)]
+    pub swap_treatment: SwapTreatment,
+    /// Fiscal year used by the yearly reports, calendar year by default.
+    #[serde(default, rename = "fiscal_year_start")]
+    pub fiscal_year: FiscalYear,
+    /// Supported fiat currencies & the base reporting currency.
+    #[serde(default)]
+    pub fiat: FiatSettings,
+}
+
+impl LedgerSettings {
+    /// Holding period classification in effect, `None` if disposals aren't classified.
+    pub fn effective_holding_period(&self) -> Option<HoldingPeriodSettings> {
+        self.tax_profile
+            .and_then(|profile| profile.holding_period())
+            .or(self.holding_period)
+    }
+
+    /// Check that the settings are consistent, e.g. that the tax profile supports the swap treatment.
+    pub fn validate(&self) -> Result<(), String> {
+        self.fiat.validate()?;
+
+        // Swaps are disposals under German law, so they must be valued to be part of the private sales.
+        if self.tax_profile == Some(TaxProfile::Germany)
+            && self.swap_treatment != SwapTreatment::MarketValue
+        {
+            return Err(
+                "German tax profile requires the swaps to be taxed at the market value, set `swap_treatment = \"marketvalue\"`."
+                    .to_string(),
+            );
+        }
+
+        // Exemption limits & the Freigrenze are defined in EUR.
+        if self.tax_profile == Some(TaxProfile::Germany)
+            && self.fiat.base_currency != AssetType::from("EUR".to_string())
+        {
+            return Err(format!(
+                "German tax profile requires EUR as the base currency, but {} is used.",
+                self.fiat.base_currency
+            ));
+        }
+
+        Ok(())
     }
 }
 
@@ -269,28 +629,73 @@ pub struct Ledger<'a> {
     /// List of all transactions, in order.
     transactions: Vec<Transaction>,
     /// Ledger of assets, used to keep track of the FIFO inventory.
-    ledger: HashMap<AssetType, Vec<InventoryItem>>,
-    /// Next index for each asset type, used to skip over the already consumed items in the ledger.
-    next_idx: HashMap<AssetType, usize>,
+    ledger: HashMap<HoldingKey, Vec<InventoryItem>>,
+    /// Next index for each holding, used to skip over the already consumed items in the ledger.
+    next_idx: HashMap<HoldingKey, usize>,
+    /// Difference between the total cost of the open lots & the cost of the weighted average pool, per holding.
+    pool_offsets: HashMap<HoldingKey, Decimal>,
+    /// Settings used to process the transactions.
+    settings: LedgerSettings,
     /// Cache of the inventory items, sorted in order their respective transactions appear.
     /// Used to avoid sorting the items multiple times.
     in_order: OnceCell<Vec<&'a InventoryItem>>,
+    /// Wash sale replacements acquired after the loss sale, by the ordinal of the acquiring transaction.
+    pending_replacements: HashMap<u32, Vec<wash_sale::Replacement>>,
+    /// Market values of the swaps taxed at the market value, by the ordinal of the swap.
+    swap_values: HashMap<u32, Decimal>,
+    /// Id of the next created lot.
+    next_lot_id: LotId,
+    /// Cache of the inventory items, by their lot Id.
+    by_lot_id: OnceCell<HashMap<LotId, &'a InventoryItem>>,
 }
 
 impl<'a> Ledger<'a> {
     /// Create a new `Ledger` instance.
-    pub fn new(transactions: Vec<Transaction>) -> Self {
-        let mut ledger = Ledger {
+    ///
+    /// # Arguments
+    /// * `transactions` - A list of validated transactions, in ascending order.
+    /// * `settings` - Settings used to process the transactions.
+    /// * `prices` - Prices used to value the swaps, only required if they're taxed at the market value.
+    ///
+    /// # Returns
+    /// * `LedgerError` - In case any transaction consumes more than is available in the inventory,
+    ///   or a swap can't be valued.
+    pub fn new(
+        transactions: Vec<Transaction>,
+        settings: LedgerSettings,
+        prices: Option<&PriceTable>,
+    ) -> Result<Self, LedgerError> {
+        let swap_values = match settings.swap_treatment {
+            SwapTreatment::Carryover => HashMap::new(),
+            SwapTreatment::MarketValue => swap_values(&transactions, prices)?,
+        };
+
+        let mut ledger = Ledger::empty(settings, swap_values);
+        ledger.process(&transactions, transactions.len())?;
+        ledger.transactions = transactions;
+
+        Ok(ledger)
+    }
+
+    /// Create a new `Ledger` instance, without any processed transactions.
+    fn empty(settings: LedgerSettings, swap_values: HashMap<u32, Decimal>) -> Self {
+        Ledger {
             transactions: Vec::new(), // ugly, maybe improve later
             ledger: HashMap::new(),
             next_idx: HashMap::new(),
+            pool_offsets: HashMap::new(),
+            settings,
             in_order: OnceCell::new(),
-        };
-
-        ledger.process(&transactions);
-        ledger.transactions = transactions;
+            pending_replacements: HashMap::new(),
+            swap_values,
+            next_lot_id: LotId::FIRST,
+            by_lot_id: OnceCell::new(),
+        }
+    }
 
-        ledger
+    /// List of all processed transactions, in order.
+    pub fn transactions(&self) -> &[Transaction] {
+        &self.transactions
     }
 
     /// Vector of `InventoryItem` references, sorted in order their respective transactions appear.
@@ -302,7 +707,8 @@ impl<'a> Ledger<'a> {
                 .flat_map(|asset_items| asset_items.iter())
                 .collect();
 
-            items.sort_by_key(|item| item.ordinal);
+            // Fee items follow the other items of the transaction.
+            items.sort_by_key(|item| (item.ordinal, item.is_fee));
             items
         })
     }
@@ -316,17 +722,32 @@ impl<'a> Ledger<'a> {
         })
     }
 
-    /// Yearly income & loss report.
+    /// Yearly income & loss report, per configured fiscal year.
     pub fn yearly_income_loss_report(&'a self) -> Vec<String> {
+        self.yearly_reports()
+            .iter()
+            .map(|report| format!("{report}"))
+            .collect()
+    }
+
+    /// Yearly income & loss reports, per configured fiscal year, sorted by year.
+    pub fn yearly_reports(&'a self) -> Vec<YearlyReport> {
+        let fiscal_year = self.settings.fiscal_year;
         let mut total_report = HashMap::<Year, YearlyReport>::new();
 
         for item in self.in_order() {
-            let year = item.date.year();
+            let year = fiscal_year.start_year(item.date);
             let report = total_report.entry(year).or_insert_with(|| YearlyReport {
-                year,
+                label: fiscal_year.label(year),
                 sell_income: Decimal::ZERO,
                 interest_income: Decimal::ZERO,
                 expense: Decimal::ZERO,
+                fees: Decimal::ZERO,
+                holding_period_gains: self
+                    .settings
+                    .effective_holding_period()
+                    .map(|_| HoldingPeriodGains::default()),
+                disallowed_loss: self.settings.wash_sales.then_some(Decimal::ZERO),
             });
 
             // If income from asset selling exists, add it to the report.
@@ -339,6 +760,21 @@ impl<'a> Ledger<'a> {
                 report.add_expense(expense);
             }
 
+            // If part of the transaction fee is attributed to the item, add it to the report.
+            if let Some(fee) = item.fee {
+                report.add_fee(fee);
+            }
+
+            // If the disposal is classified by the holding period, add its gain to the respective class.
+            if let (Some(term), Some(profit)) = (item.holding_term, item.profit()) {
+                report.add_holding_period_gain(term, profit);
+            }
+
+            // If the loss was disallowed by the wash sale rule, add it to the report.
+            if let Some(disallowed_loss) = item.disallowed_loss {
+                report.add_disallowed_loss(disallowed_loss);
+            }
+
             // If the item was acquired via interest, add its income to the report.
             if item.is_interest {
                 report.add_interest_income(item.input_amount);
@@ -348,7 +784,7 @@ impl<'a> Ledger<'a> {
         total_report
             .into_iter()
             .sorted_by_key(|(year, _)| *year)
-            .map(|(_, report)| format!("{report}"))
+            .map(|(_, report)| report)
             .collect()
     }
 
@@ -365,34 +801,69 @@ impl<'a> Ledger<'a> {
     /// Process a list of transactions.
     ///
     /// Caller must ensure they are sorted, and are generally correct.
-    fn process(&mut self, transactions: &Vec<Transaction>) {
-        for transaction in transactions {
-            self.add_transaction(transaction);
+    ///
+    /// Only the first `count` transactions are added, the rest are only looked ahead to by the wash sale rule.
+    fn process(&mut self, transactions: &[Transaction], count: usize) -> Result<(), LedgerError> {
+        for (idx, transaction) in transactions.iter().enumerate().take(count) {
+            self.add_transaction(transaction, &transactions[idx + 1..])?;
         }
+
+        Ok(())
     }
 
     /// Add a new transaction to the ledger.
-    fn add_transaction(&mut self, transaction: &Transaction) {
+    ///
+    /// Transactions following it are only used by the wash sale rule.
+    fn add_transaction(
+        &mut self,
+        transaction: &Transaction,
+        upcoming: &[Transaction],
+    ) -> Result<(), LedgerError> {
+        // Fee is paid first, so the crypto fees are paid from the assets held before the transaction.
+        let fee = self.pay_fee(transaction)?;
+
         match transaction.tx_type() {
             TransactionType::Buying | TransactionType::Invoice | TransactionType::Interest => {
-                self.process_inflow(transaction);
+                self.process_inflow(transaction, fee)?;
             }
             TransactionType::Selling | TransactionType::Swap => {
-                self.process_swap_or_outflow(transaction);
+                self.process_swap_or_outflow(transaction, fee, upcoming)?;
+            }
+            TransactionType::Transfer => {
+                self.process_transfer(transaction, fee)?;
             }
         }
+
+        Ok(())
+    }
+
+    /// Key of the holding in which the asset is tracked.
+    fn holding_key(&self, asset: AssetType, account: Option<&str>) -> HoldingKey {
+        HoldingKey::new(asset, account, self.settings.per_account_inventory)
+    }
+
+    /// Holding period class of the disposal of an asset held since `held_since`, if configured.
+    fn holding_term(&self, held_since: NaiveDate, disposed: NaiveDate) -> Option<HoldingTerm> {
+        self.settings
+            .effective_holding_period()
+            .map(|settings| settings.classify(held_since, disposed))
     }
 
     /// Process a transaction which involves acquiring new crypto assets.
-    /// Input, regardless of the type, is always fiat (EUR).
-    fn process_inflow(&mut self, transaction: &Transaction) {
+    /// Input, regardless of the type, is always fiat (base currency).
+    fn process_inflow(
+        &mut self,
+        transaction: &Transaction,
+        fee: Option<Decimal>,
+    ) -> Result<(), LedgerError> {
         let (input_token, input_amount) = transaction.input();
         let (output_token, output_amount) = transaction.output();
 
-        let entry = self.ledger.entry(output_token.clone()).or_default();
+        let key = self.holding_key(output_token.clone(), transaction.receiving_account());
 
         // Create a new inventory item for the transaction.
-        let item = InventoryItem {
+        let mut item = InventoryItem {
+            lot_id: self.next_lot_id(),
             ordinal: transaction.ordinal(),
             date: transaction.date(),
             acquisition_date: transaction.date(),
@@ -402,103 +873,531 @@ impl<'a> Ledger<'a> {
             output_amount,
             remaining_amount: output_amount,
             cost_basis: transaction.cost_basis(),
+            input_cost_basis: None,
             sale_price: None,
+            holding_term: None,
+            disallowed_loss: None,
+            wash_sale_adjustment: None,
+            replaced_amount: Decimal::ZERO,
+            holding_period_start: None,
+            fee: None,
+            parent_lot: None,
             parent_tx: None,
             is_interest: transaction.tx_type() == TransactionType::Interest,
+            is_transfer: false,
+            is_fee: false,
+            account: transaction.receiving_account().map(str::to_string),
         };
-        entry.push(item);
+        if let Some(fee) = fee {
+            fee::split_fee(fee, std::slice::from_mut(&mut item), false, transaction)?;
+        }
+        let mut items = vec![item];
+        self.apply_pending_replacement(transaction, &mut items);
+
+        self.ledger.entry(key).or_default().extend(items);
+
+        Ok(())
     }
 
     /// Process a transaction which involves selling crypto for fiat or a swap.
-    fn process_swap_or_outflow(&mut self, transaction: &Transaction) {
+    fn process_swap_or_outflow(
+        &mut self,
+        transaction: &Transaction,
+        fee: Option<Decimal>,
+        upcoming: &[Transaction],
+    ) -> Result<(), LedgerError> {
         let (input_token, input_amount) = transaction.input();
         let (output_token, output_amount) = transaction.output();
 
-        let start_idx = *self.next_idx.get(&input_token).unwrap_or(&0);
-        let inventory = self
-            .ledger
-            .get_mut(&input_token)
-            .expect("Must exist since data was validated.");
-        let mut remaining_input_amount = input_amount;
+        let input_key = self.holding_key(input_token.clone(), transaction.account());
+        let consumed_lots = self.consume_lots(&input_key, input_amount, transaction)?;
+        let lots_count = consumed_lots.len();
         let mut remaining_output_amount = output_amount;
 
-        let mut new_items = Vec::new();
-
-        for (offset, item) in inventory.iter_mut().skip(start_idx).enumerate() {
-            if remaining_input_amount.is_zero() {
-                break;
-            }
-
-            let consumed_amount = if item.remaining_amount > remaining_input_amount {
-                // Consume the entire amount.
-                let consumed = remaining_input_amount;
-                item.remaining_amount -= consumed;
-                remaining_input_amount = Decimal::ZERO;
-
-                consumed
-            } else {
-                // Consume the remaining amount.
-                let consumed = item.remaining_amount;
-                remaining_input_amount -= item.remaining_amount;
-                item.remaining_amount = Decimal::ZERO;
+        // Swaps taxed at the market value are disposals, same as the sales.
+        let swap_value = self.swap_values.get(&transaction.ordinal()).copied();
+        let sale_price = match swap_value {
+            Some(value) => Some(value / input_amount),
+            None => transaction.sale_price(&self.settings.fiat),
+        };
 
-                consumed
-            };
+        let mut new_items = Vec::new();
 
-            // Once remaining input amount reaches zero, consume the entire remaining output amount.
-            let new_amount = if remaining_input_amount.is_zero() {
+        for (idx, lot) in consumed_lots.into_iter().enumerate() {
+            // The last fragment consumes the entire remaining output amount.
+            let new_amount = if idx + 1 == lots_count {
                 remaining_output_amount
             } else {
-                let new_amount = output_amount * consumed_amount / input_amount;
+                let new_amount = output_amount * lot.amount / input_amount;
                 remaining_output_amount -= new_amount;
 
                 new_amount
             };
 
-            let new_cost_basis = if output_token.is_fiat() {
-                item.cost_basis()
-            } else {
-                transaction.cost_basis() * item.cost_basis()
+            let (new_cost_basis, input_cost_basis) = match swap_value {
+                // Market value of the swap is the cost basis of the acquired asset.
+                Some(value) => (value / output_amount, Some(lot.cost_basis)),
+                None if self.settings.fiat.is_fiat(&output_token) => (lot.cost_basis, None),
+                None => (transaction.cost_basis() * lot.cost_basis, None),
             };
 
             let new_item = InventoryItem {
+                lot_id: self.next_lot_id(),
                 ordinal: transaction.ordinal(),
                 date: transaction.date(),
-                acquisition_date: item.date,
+                acquisition_date: lot.held_since,
                 input_type: input_token.clone(),
-                input_amount: consumed_amount,
+                input_amount: lot.amount,
                 output_type: output_token.clone(),
                 output_amount: new_amount,
                 remaining_amount: new_amount,
-                // Chaining rule applies here.
+                // Chaining rule applies here, unless the swap is taxed at the market value.
                 cost_basis: new_cost_basis,
-                sale_price: transaction.sale_price(),
-                parent_tx: Some(transaction.ordinal() as usize),
+                input_cost_basis,
+                sale_price,
+                holding_term: sale_price
+                    .and_then(|_| self.holding_term(lot.held_since, transaction.date())),
+                disallowed_loss: None,
+                wash_sale_adjustment: None,
+                replaced_amount: Decimal::ZERO,
+                holding_period_start: None,
+                fee: None,
+                parent_lot: Some(lot.lot_id),
+                parent_tx: Some(lot.ordinal),
                 is_interest: false,
+                is_transfer: false,
+                is_fee: false,
+                account: transaction.receiving_account().map(str::to_string),
             };
 
             new_items.push(new_item);
+        }
 
-            // Update the next index to skip over fully consumed items.
-            if item.remaining_amount.is_zero() {
-                self.next_idx
-                    .insert(input_token.clone(), start_idx + offset + 1);
-            }
+        if let Some(fee) = fee {
+            fee::split_fee(fee, &mut new_items, sale_price.is_some(), transaction)?;
         }
 
-        if !remaining_input_amount.is_zero() {
-            log::error!(
-                "Remaining amount of {} for {:?} after processing transaction: {}",
-                remaining_input_amount,
-                input_token,
-                transaction
-            );
+        // Swaps taxed at the market value are sales as well, so their losses can be disallowed too.
+        if self.settings.wash_sales && sale_price.is_some() {
+            for item in new_items.iter_mut() {
+                self.apply_wash_sale(item, upcoming);
+            }
+        }
+        if !self.settings.fiat.is_fiat(&output_token) {
+            self.apply_pending_replacement(transaction, &mut new_items);
         }
 
         // Add the new items to the ledger.
-        self.ledger
-            .entry(output_token.clone())
-            .or_default()
-            .extend(new_items);
+        let output_key = self.holding_key(output_token, transaction.receiving_account());
+        self.ledger.entry(output_key).or_default().extend(new_items);
+
+        Ok(())
+    }
+
+    /// Process a transaction which moves an asset between own wallets or exchanges.
+    ///
+    /// Transferred lots keep their original acquisition date & cost basis.
+    /// In case inventories are kept per account, lots are moved to the inventory of the target account.
+    /// The difference between the sent and the received amount is treated as a fee,
+    /// and is booked as a disposal at zero proceeds.
+    /// Explicit fee of the transfer is added to the cost basis of the transferred lots instead.
+    fn process_transfer(
+        &mut self,
+        transaction: &Transaction,
+        fee: Option<Decimal>,
+    ) -> Result<(), LedgerError> {
+        let (token, sent_amount) = transaction.input();
+        let (_, received_amount) = transaction.output();
+        let fee_amount = sent_amount - received_amount;
+
+        let source_key = self.holding_key(token.clone(), transaction.account());
+        let target_key = self.holding_key(token.clone(), transaction.receiving_account());
+
+        let transferred_lots = self.consume_lots(&source_key, received_amount, transaction)?;
+        let fee_lots = if fee_amount.is_zero() {
+            Vec::new()
+        } else {
+            self.consume_lots(&source_key, fee_amount, transaction)?
+        };
+
+        let lot_ids: Vec<_> = (0..transferred_lots.len() + fee_lots.len())
+            .map(|_| self.next_lot_id())
+            .collect();
+        let new_item = |lot: &ConsumedLot, is_fee: bool, lot_id: LotId| InventoryItem {
+            lot_id,
+            ordinal: transaction.ordinal(),
+            date: transaction.date(),
+            acquisition_date: lot.held_since,
+            input_type: token.clone(),
+            input_amount: lot.amount,
+            output_type: token.clone(),
+            output_amount: if is_fee { Decimal::ZERO } else { lot.amount },
+            remaining_amount: if is_fee { Decimal::ZERO } else { lot.amount },
+            cost_basis: lot.cost_basis,
+            input_cost_basis: None,
+            sale_price: is_fee.then_some(Decimal::ZERO),
+            holding_term: is_fee
+                .then(|| self.holding_term(lot.held_since, transaction.date()))
+                .flatten(),
+            disallowed_loss: None,
+            wash_sale_adjustment: None,
+            // Transferred replacement lots can't replace another loss sale.
+            replaced_amount: if is_fee {
+                Decimal::ZERO
+            } else {
+                lot.replaced_amount
+            },
+            holding_period_start: None,
+            fee: None,
+            parent_lot: Some(lot.lot_id),
+            parent_tx: Some(lot.ordinal),
+            is_interest: false,
+            is_transfer: true,
+            is_fee,
+            account: transaction.receiving_account().map(str::to_string),
+        };
+
+        let mut new_items: Vec<_> = transferred_lots
+            .iter()
+            .map(|lot| (lot, false))
+            .chain(fee_lots.iter().map(|lot| (lot, true)))
+            .zip(lot_ids)
+            .map(|((lot, is_fee), lot_id)| new_item(lot, is_fee, lot_id))
+            .collect();
+        if let Some(fee) = fee {
+            fee::split_fee(fee, &mut new_items, false, transaction)?;
+        }
+
+        // Transferred lots are inserted according to their acquisition date, to preserve the lot ordering.
+        let start_idx = *self.next_idx.get(&target_key).unwrap_or(&0);
+        let inventory = self.ledger.entry(target_key).or_default();
+        for item in new_items {
+            let position = inventory
+                .iter()
+                .skip(start_idx)
+                .position(|existing| existing.held_since() > item.held_since())
+                .map_or(inventory.len(), |offset| start_idx + offset);
+            inventory.insert(position, item);
+        }
+
+        Ok(())
+    }
+
+    /// Consume the specified amount of the holding from the inventory, using the configured cost basis method.
+    ///
+    /// Returns the consumed parts of the lots, in the order of consumption.
+    fn consume_lots(
+        &mut self,
+        key: &HoldingKey,
+        amount: Decimal,
+        transaction: &Transaction,
+    ) -> Result<Vec<ConsumedLot>, LedgerError> {
+        let error = |kind| LedgerError {
+            ordinal: transaction.ordinal(),
+            location: transaction.location().clone(),
+            kind,
+        };
+
+        let start_idx = *self.next_idx.get(key).unwrap_or(&0);
+        // Weighted average disposals consume the lots at the average cost basis of the pool.
+        let pool_cost_basis = self.pool_cost_basis(key);
+        let inventory = self.ledger.get_mut(key).ok_or_else(|| {
+            error(LedgerErrorKind::UnknownHolding {
+                holding: key.clone(),
+            })
+        })?;
+        let method = self.settings.cost_basis_method;
+        let mut remaining_amount = amount;
+        let mut pool_offset_change = Decimal::ZERO;
+
+        let mut consumed_lots = Vec::new();
+
+        for idx in method.consumption_order(inventory, start_idx) {
+            if remaining_amount.is_zero() {
+                break;
+            }
+
+            let item = &mut inventory[idx];
+
+            let consumed_amount = if item.remaining_amount > remaining_amount {
+                // Consume the entire amount.
+                let consumed = remaining_amount;
+                item.remaining_amount -= consumed;
+                remaining_amount = Decimal::ZERO;
+
+                consumed
+            } else {
+                // Consume the remaining amount.
+                let consumed = item.remaining_amount;
+                remaining_amount -= item.remaining_amount;
+                item.remaining_amount = Decimal::ZERO;
+
+                consumed
+            };
+            // Replaced amount can't exceed what's left of the lot.
+            let replaced_amount = item.replaced_amount.min(consumed_amount);
+            item.replaced_amount = item.replaced_amount.min(item.remaining_amount);
+
+            let cost_basis = match pool_cost_basis {
+                Some(pool_cost_basis) => {
+                    pool_offset_change += consumed_amount * (pool_cost_basis - item.cost_basis());
+                    pool_cost_basis
+                }
+                None => item.cost_basis(),
+            };
+
+            consumed_lots.push(ConsumedLot {
+                lot_id: item.lot_id,
+                ordinal: item.ordinal,
+                held_since: item.held_since(),
+                cost_basis,
+                amount: consumed_amount,
+                replaced_amount,
+            });
+        }
+        if !pool_offset_change.is_zero() {
+            *self.pool_offsets.entry(key.clone()).or_default() += pool_offset_change;
+        }
+
+        // Update the next index to skip over fully consumed items.
+        // Other methods than FIFO can leave open lots behind, so only the leading consumed items are skipped.
+        let next_idx = inventory
+            .iter()
+            .enumerate()
+            .skip(start_idx)
+            .find(|(_, item)| !item.remaining_amount.is_zero())
+            .map_or(inventory.len(), |(idx, _)| idx);
+        self.next_idx.insert(key.clone(), next_idx);
+
+        if !remaining_amount.is_zero() {
+            return Err(error(LedgerErrorKind::InsufficientLots {
+                holding: key.clone(),
+                missing: remaining_amount,
+            }));
+        }
+
+        Ok(consumed_lots)
+    }
+
+    /// Average unit cost basis of the holding, if its lots are pooled by the cost basis method.
+    fn pool_cost_basis(&self, key: &HoldingKey) -> Option<Decimal> {
+        let lots = self.ledger.get(key)?;
+        let start_idx = *self.next_idx.get(key).unwrap_or(&0);
+        let pool_offset = self.pool_offsets.get(key).copied().unwrap_or_default();
+
+        self.settings
+            .cost_basis_method
+            .pool_cost_basis(lots, start_idx, pool_offset)
+    }
+}
+
+/// Part of an inventory lot consumed by a transaction.
+struct ConsumedLot {
+    /// Id of the consumed lot.
+    lot_id: LotId,
+    /// Ordinal of the transaction which created the consumed lot.
+    ordinal: u32,
+    /// Date since which the consumed asset is held.
+    held_since: NaiveDate,
+    /// Unit cost basis of the consumed asset.
+    cost_basis: Decimal,
+    /// Consumed amount.
+    amount: Decimal,
+    /// Part of the consumed amount used as a wash sale replacement.
+    replaced_amount: Decimal,
+}
+
+/// Market values of the swaps, by the ordinal of the swap.
+///
+/// # Returns
+/// * `LedgerError` - In case any swap can't be valued.
+fn swap_values(
+    transactions: &[Transaction],
+    prices: Option<&PriceTable>,
+) -> Result<HashMap<u32, Decimal>, LedgerError> {
+    transactions
+        .iter()
+        .filter(|transaction| transaction.tx_type() == TransactionType::Swap)
+        .map(|transaction| {
+            prices
+                .and_then(|prices| prices.market_value(transaction))
+                .map(|value| (transaction.ordinal(), value))
+                .ok_or_else(|| LedgerError {
+                    ordinal: transaction.ordinal(),
+                    location: transaction.location().clone(),
+                    kind: LedgerErrorKind::MissingPrice {
+                        input: transaction.input().0,
+                        output: transaction.output().0,
+                        date: transaction.date(),
+                    },
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_utils::{asset, date, dec, tx},
+        types::TransactionType::{Buying, Selling, Swap, Transfer},
+    };
+
+    fn market_value_settings() -> LedgerSettings {
+        LedgerSettings {
+            swap_treatment: SwapTreatment::MarketValue,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn transfer_fee_is_booked_as_fee_disposal() {
+        let transactions = vec![
+            tx(1, "2024-01-01", Buying, ("EUR", "200"), ("BTC", "2")),
+            tx(2, "2024-02-01", Transfer, ("BTC", "2"), ("BTC", "1.9")),
+        ];
+        let ledger = Ledger::new(transactions, LedgerSettings::default(), None).unwrap();
+        let items = ledger.in_order();
+        assert_eq!(items.len(), 3);
+
+        let transferred = items[1];
+        assert!(!transferred.is_fee());
+        assert_eq!(transferred.remaining_amount(), dec("1.9"));
+        assert_eq!(transferred.expense(), None);
+
+        // Difference between the sent & received amount is a disposal at zero proceeds.
+        let fee = items[2];
+        assert!(fee.is_fee());
+        assert_eq!(fee.input(), (&asset("BTC"), dec("0.1")));
+        assert_eq!(fee.remaining_amount(), Decimal::ZERO);
+        assert_eq!(fee.income(), Some(Decimal::ZERO));
+        assert_eq!(fee.expense(), Some(dec("10")));
+        assert_eq!(fee.output_line(ledger.get_tx(fee)).action(), "Fee");
+    }
+
+    #[test]
+    fn market_value_swap_is_a_disposal_at_the_market_value() {
+        let mut prices = PriceTable::default();
+        prices.insert(asset("ETH"), date("2024-01-15"), dec("15"));
+        let transactions = vec![
+            tx(1, "2024-01-01", Buying, ("EUR", "200"), ("BTC", "2")),
+            tx(2, "2024-02-01", Swap, ("BTC", "1"), ("ETH", "10")),
+            tx(3, "2024-03-01", Selling, ("ETH", "10"), ("EUR", "160")),
+        ];
+        let ledger = Ledger::new(transactions, market_value_settings(), Some(&prices)).unwrap();
+        let items = ledger.in_order();
+        assert_eq!(items.len(), 3);
+
+        // Swapped lot is expensed at its own cost basis, while the acquired lot is valued at the market.
+        let swap = items[1];
+        assert_eq!(swap.output(), (&asset("ETH"), dec("10")));
+        assert_eq!(swap.cost_basis(), dec("15"));
+        assert_eq!(swap.expense(), Some(dec("100")));
+        assert_eq!(swap.income(), Some(dec("150")));
+        assert_eq!(swap.profit(), Some(dec("50")));
+
+        let sale = items[2];
+        assert_eq!(sale.expense(), Some(dec("150")));
+        assert_eq!(sale.profit(), Some(dec("10")));
+    }
+
+    #[test]
+    fn missing_swap_price_names_both_assets() {
+        let mut prices = PriceTable::default();
+        prices.insert(asset("ETH"), date("2024-03-01"), dec("15"));
+        let transactions = vec![
+            tx(1, "2024-01-01", Buying, ("EUR", "200"), ("BTC", "2")),
+            tx(2, "2024-02-01", Swap, ("BTC", "1"), ("ETH", "10")),
+        ];
+        let error = Ledger::new(transactions, market_value_settings(), Some(&prices)).unwrap_err();
+
+        assert_eq!(error.ordinal, 2);
+        assert_eq!(
+            error.kind,
+            LedgerErrorKind::MissingPrice {
+                input: asset("BTC"),
+                output: asset("ETH"),
+                date: date("2024-02-01"),
+            }
+        );
+        assert_eq!(
+            error.kind.to_string(),
+            "No price available for either ETH or BTC on or before 2024-02-01"
+        );
+    }
+
+    #[test]
+    fn lots_are_consumed_from_the_selling_account() {
+        let transactions = vec![
+            tx(1, "2024-01-01", Buying, ("EUR", "100"), ("BTC", "1"))
+                .new_with_accounts(Some("Kraken".to_string()), None),
+            tx(2, "2024-01-02", Buying, ("EUR", "300"), ("BTC", "1"))
+                .new_with_accounts(Some("Ledger".to_string()), None),
+            tx(3, "2024-01-03", Selling, ("BTC", "1"), ("EUR", "400"))
+                .new_with_accounts(Some("Ledger".to_string()), None),
+        ];
+        let settings = LedgerSettings {
+            per_account_inventory: true,
+            ..Default::default()
+        };
+        let ledger = Ledger::new(transactions.clone(), settings, None).unwrap();
+
+        let sale = ledger.in_order()[2];
+        assert_eq!(sale.account(), Some("Ledger"));
+        assert_eq!(sale.expense(), Some(dec("300")));
+        assert_eq!(ledger.in_order()[0].remaining_amount(), dec("1"));
+
+        // Without per-account inventories, the oldest lot of any account is sold.
+        let ledger = Ledger::new(transactions, LedgerSettings::default(), None).unwrap();
+        assert_eq!(ledger.in_order()[2].expense(), Some(dec("100")));
+    }
+
+    #[test]
+    fn selling_from_an_account_without_lots_is_an_error() {
+        let transactions = vec![
+            tx(1, "2024-01-01", Buying, ("EUR", "100"), ("BTC", "1"))
+                .new_with_accounts(Some("Kraken".to_string()), None),
+            tx(2, "2024-01-02", Selling, ("BTC", "1"), ("EUR", "200"))
+                .new_with_accounts(Some("Ledger".to_string()), None),
+        ];
+        let settings = LedgerSettings {
+            per_account_inventory: true,
+            ..Default::default()
+        };
+        let error = Ledger::new(transactions, settings, None).unwrap_err();
+
+        assert_eq!(error.ordinal, 2);
+        assert!(matches!(
+            error.kind,
+            LedgerErrorKind::UnknownHolding { holding } if holding.account.as_deref() == Some("Ledger")
+        ));
+    }
+
+    #[test]
+    fn yearly_reports_are_split_by_the_fiscal_year() {
+        let transactions = vec![
+            tx(1, "2023-03-01", Buying, ("EUR", "300"), ("BTC", "3")),
+            tx(2, "2023-06-30", Selling, ("BTC", "1"), ("EUR", "150")),
+            tx(3, "2023-07-01", Selling, ("BTC", "1"), ("EUR", "200")),
+            tx(4, "2024-06-30", Selling, ("BTC", "1"), ("EUR", "250")),
+        ];
+        let settings = LedgerSettings {
+            fiscal_year: FiscalYear::new(7, 1).unwrap(),
+            ..Default::default()
+        };
+        let ledger = Ledger::new(transactions, settings, None).unwrap();
+
+        let found: Vec<_> = ledger
+            .yearly_reports()
+            .into_iter()
+            .map(|report| (report.label, report.sell_income))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("2022/23".to_string(), dec("150")),
+                ("2023/24".to_string(), dec("450")),
+            ]
+        );
     }
 }
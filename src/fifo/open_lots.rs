@@ -0,0 +1,288 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Report of the open lots, i.e. the remaining inventory carried forward.
+
+use super::{InventoryItem, Ledger};
+use crate::types::{AssetType, CsvField, LedgerError};
+
+use chrono::NaiveDate;
+use itertools::Itertools;
+use rust_decimal::Decimal;
+use std::fmt::{self, Display, Formatter};
+
+/// Lot which isn't fully consumed yet.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct OpenLot {
+    /// Held asset.
+    pub asset: AssetType,
+    /// Account (wallet or exchange) holding the lot, if known.
+    pub account: Option<String>,
+    /// Date since which the asset is held.
+    pub acquisition_date: NaiveDate,
+    /// Ordinal number of the transaction which acquired the lot.
+    /// Transfers keep the ordinal of the original acquisition.
+    pub ordinal: u32,
+    /// Amount which wasn't consumed yet.
+    pub remaining_amount: Decimal,
+    /// Unit cost basis of the lot.
+    pub cost_basis: Decimal,
+}
+
+impl OpenLot {
+    /// Elements of the CSV header, matching the `csv_line_elements`.
+    pub const CSV_HEADER_ELEMENTS: [&'static str; 7] = [
+        "Asset",
+        "Account",
+        "Acquisition Date",
+        "Ordinal",
+        "Remaining Amount",
+        "Cost Basis",
+        "Total Cost",
+    ];
+
+    /// Total cost of the remaining amount.
+    pub fn total_cost(&self) -> Decimal {
+        self.remaining_amount * self.cost_basis
+    }
+
+    /// Elements of the single line in the CSV file.
+    pub fn csv_line_elements(&self) -> Vec<CsvField> {
+        vec![
+            self.asset.to_string().into(),
+            self.account.clone().unwrap_or_default().into(),
+            self.acquisition_date.format("%d.%m.%Y").to_string().into(),
+            self.ordinal.to_string().into(),
+            self.remaining_amount.into(),
+            self.cost_basis.into(),
+            self.total_cost().into(),
+        ]
+    }
+}
+
+/// Total of the open lots of a single asset.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct OpenLotsTotal {
+    /// Held asset.
+    pub asset: AssetType,
+    /// Total remaining amount.
+    pub amount: Decimal,
+    /// Total cost of the remaining amount.
+    pub total_cost: Decimal,
+}
+
+impl Display for OpenLotsTotal {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: Amount: {}, Total Cost: {:.2}",
+            self.asset, self.amount, self.total_cost
+        )
+    }
+}
+
+impl<'a> Ledger<'a> {
+    /// Open lots at the end of the cutoff date, sorted by asset, account & acquisition date.
+    ///
+    /// In case there's no cutoff date, open lots after all transactions are returned.
+    /// Otherwise the transactions up to (and including) the cutoff date are processed again,
+    /// so the later disposals don't affect the report.
+    /// Later transactions are still looked ahead to by the wash sale rule, same as when the ledger was created,
+    /// so the replayed state matches the state of the ledger at the cutoff date.
+    ///
+    /// # Returns
+    /// * `LedgerError` - In case the replayed transactions can't be processed.
+    pub fn open_lots(&'a self, cutoff: Option<NaiveDate>) -> Result<Vec<OpenLot>, LedgerError> {
+        let prefix_len = match cutoff {
+            Some(cutoff) => self.transactions.partition_point(|tx| tx.date() <= cutoff),
+            None => self.transactions.len(),
+        };
+
+        if prefix_len == self.transactions.len() {
+            return Ok(self.collect_open_lots());
+        }
+
+        let mut ledger = Ledger::empty(self.settings.clone(), self.swap_values.clone());
+        ledger.process(&self.transactions, prefix_len)?;
+        Ok(ledger.collect_open_lots())
+    }
+
+    /// Collect the open lots of the current inventory.
+    fn collect_open_lots(&'a self) -> Vec<OpenLot> {
+        self.ledger
+            .iter()
+            .flat_map(|(key, items)| {
+                // Pooled lots are carried at the average cost basis of the pool.
+                let pool_cost_basis = self.pool_cost_basis(key);
+                items.iter().map(move |item| (item, pool_cost_basis))
+            })
+            // Fiat received from selling is not part of the inventory.
+            .filter(|(item, _)| {
+                self.settings.fiat.is_crypto(&item.output_type) && !item.remaining_amount.is_zero()
+            })
+            .map(|(item, pool_cost_basis)| OpenLot {
+                asset: item.output_type.clone(),
+                account: item.account.clone(),
+                acquisition_date: item.held_since(),
+                ordinal: self.acquiring_ordinal(item),
+                remaining_amount: item.remaining_amount,
+                cost_basis: pool_cost_basis.unwrap_or(item.cost_basis),
+            })
+            .sorted_by(|a, b| {
+                (&a.asset, &a.account, a.acquisition_date, a.ordinal).cmp(&(
+                    &b.asset,
+                    &b.account,
+                    b.acquisition_date,
+                    b.ordinal,
+                ))
+            })
+            .collect()
+    }
+
+    /// Ordinal of the transaction which acquired the item, following the transferred lots back to the acquisition.
+    fn acquiring_ordinal(&'a self, item: &'a InventoryItem) -> u32 {
+        std::iter::successors(Some(item), |item| {
+            item.is_transfer
+                .then(|| item.parent_lot.and_then(|parent| self.lot(parent)))
+                .flatten()
+        })
+        .last()
+        .map_or(item.ordinal, |acquisition| acquisition.ordinal)
+    }
+}
+
+/// Totals of the open lots per asset, sorted by asset.
+pub fn open_lots_totals(lots: &[OpenLot]) -> Vec<OpenLotsTotal> {
+    lots.iter()
+        .into_group_map_by(|lot| lot.asset.clone())
+        .into_iter()
+        .sorted_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(asset, lots)| OpenLotsTotal {
+            asset,
+            amount: lots.iter().map(|lot| lot.remaining_amount).sum(),
+            total_cost: lots.iter().map(|lot| lot.total_cost()).sum(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fifo::LedgerSettings,
+        test_utils::{asset, date, dec, ledger, tx},
+        types::{
+            Transaction,
+            TransactionType::{Buying, Selling, Transfer},
+        },
+    };
+
+    #[test]
+    fn open_lots_at_cutoff_ignore_later_disposals() {
+        let ledger = ledger(
+            vec![
+                tx(1, "2024-01-01", Buying, ("EUR", "100"), ("BTC", "1")),
+                tx(2, "2024-06-01", Buying, ("EUR", "300"), ("BTC", "1")),
+                tx(3, "2025-01-10", Selling, ("BTC", "1.5"), ("EUR", "600")),
+            ],
+            LedgerSettings::default(),
+        );
+
+        let year_end = ledger.open_lots(Some(date("2024-12-31"))).unwrap();
+        assert_eq!(year_end.len(), 2);
+        assert_eq!(
+            open_lots_totals(&year_end),
+            vec![OpenLotsTotal {
+                asset: asset("BTC"),
+                amount: dec("2"),
+                total_cost: dec("400"),
+            }]
+        );
+
+        let open_lots = ledger.open_lots(None).unwrap();
+        assert_eq!(open_lots.len(), 1);
+        assert_eq!(open_lots[0].ordinal, 2);
+        assert_eq!(open_lots[0].remaining_amount, dec("0.5"));
+        assert_eq!(open_lots[0].total_cost(), dec("150"));
+    }
+
+    #[test]
+    fn transferred_lots_keep_acquiring_ordinal() {
+        let settings = LedgerSettings {
+            per_account_inventory: true,
+            ..Default::default()
+        };
+        let account = |tx: Transaction, from: &str, to: Option<&str>| {
+            tx.new_with_accounts(Some(from.to_string()), to.map(str::to_string))
+        };
+        let ledger = ledger(
+            vec![
+                account(
+                    tx(1, "2024-01-01", Buying, ("EUR", "100"), ("BTC", "1")),
+                    "Exchange",
+                    None,
+                ),
+                account(
+                    tx(2, "2024-02-01", Transfer, ("BTC", "1"), ("BTC", "1")),
+                    "Exchange",
+                    Some("Wallet"),
+                ),
+                account(
+                    tx(3, "2024-03-01", Transfer, ("BTC", "1"), ("BTC", "1")),
+                    "Wallet",
+                    Some("Cold Storage"),
+                ),
+            ],
+            settings,
+        );
+
+        let open_lots = ledger.open_lots(None).unwrap();
+        assert_eq!(open_lots.len(), 1);
+        assert_eq!(open_lots[0].account.as_deref(), Some("Cold Storage"));
+        assert_eq!(open_lots[0].ordinal, 1);
+        assert_eq!(open_lots[0].acquisition_date, date("2024-01-01"));
+
+        let replayed = ledger.open_lots(Some(date("2024-02-15"))).unwrap();
+        assert_eq!(replayed.len(), 1);
+        assert_eq!(replayed[0].account.as_deref(), Some("Wallet"));
+        assert_eq!(replayed[0].ordinal, 1);
+    }
+
+    #[test]
+    fn replayed_open_lots_match_the_ledger_with_wash_sales() {
+        let settings = LedgerSettings {
+            wash_sales: true,
+            ..Default::default()
+        };
+        let ledger = ledger(
+            vec![
+                tx(1, "2024-12-01", Buying, ("EUR", "100"), ("BTC", "1")),
+                tx(2, "2024-12-20", Selling, ("BTC", "1"), ("EUR", "50")),
+                // Replacement acquired after the loss sale.
+                tx(3, "2025-01-05", Buying, ("EUR", "60"), ("BTC", "1")),
+                tx(4, "2025-03-01", Buying, ("EUR", "10"), ("ETH", "1")),
+            ],
+            settings,
+        );
+
+        let replayed = ledger.open_lots(Some(date("2025-01-31"))).unwrap();
+        let open_lots: Vec<_> = ledger
+            .open_lots(None)
+            .unwrap()
+            .into_iter()
+            .filter(|lot| lot.asset == asset("BTC"))
+            .collect();
+        assert_eq!(replayed, open_lots);
+        // Disallowed loss is added to the cost basis of the replacement.
+        assert_eq!(replayed[0].cost_basis, dec("110"));
+    }
+}
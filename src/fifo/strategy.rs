@@ -0,0 +1,170 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lot selection strategies used by the ledger when an asset is disposed of.
+//!
+//! The strategy only decides **which** lots are consumed, and in which order.
+//! Fragmentation of the consumed lots is the same for every strategy, so the produced
+//! inventory items (and the CSV output) always have the same shape.
+
+use super::InventoryItem;
+
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::fmt::{self, Display, Formatter};
+
+/// Method used to determine the cost basis of the disposed assets.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CostBasisMethod {
+    /// First-In First-Out, the oldest lots are consumed first.
+    #[default]
+    Fifo,
+    /// Last-In First-Out, the newest lots are consumed first.
+    Lifo,
+    /// Highest-In First-Out, lots with the highest unit cost basis are consumed first.
+    Hifo,
+    /// Weighted average cost, every disposal uses the average unit cost basis of all open lots.
+    /// Lots are still consumed in FIFO order, to preserve the acquisition dates.
+    #[serde(alias = "average")]
+    WeightedAverage,
+}
+
+impl CostBasisMethod {
+    /// Indices of the open lots, in the order they should be consumed.
+    ///
+    /// # Arguments
+    /// * `lots` - Inventory of a single asset, in the order lots were added.
+    /// * `start_idx` - Index of the first lot which might still be open. All lots before it are fully consumed.
+    pub(crate) fn consumption_order(&self, lots: &[InventoryItem], start_idx: usize) -> Vec<usize> {
+        let open_lots = lots
+            .iter()
+            .enumerate()
+            .skip(start_idx)
+            .filter(|(_, lot)| !lot.remaining_amount.is_zero())
+            .map(|(idx, _)| idx);
+
+        match self {
+            CostBasisMethod::Fifo | CostBasisMethod::WeightedAverage => open_lots.collect(),
+            CostBasisMethod::Lifo => open_lots.rev().collect(),
+            CostBasisMethod::Hifo => {
+                let mut order: Vec<_> = open_lots.collect();
+                // Stable sort, so lots with the same cost basis are consumed in FIFO order.
+                order.sort_by(|a, b| lots[*b].cost_basis.cmp(&lots[*a].cost_basis));
+                order
+            }
+        }
+    }
+
+    /// Average unit cost basis of the pooled open lots, used for the disposal instead of the cost basis of each lot.
+    ///
+    /// Only the weighted average method pools the lots, other methods return `None`.
+    /// Lots keep their own cost basis, so the pool cost is their total cost reduced by the `pool_offset`,
+    /// i.e. by the difference accumulated by the earlier disposals consuming the lots at the average.
+    pub(crate) fn pool_cost_basis(
+        &self,
+        lots: &[InventoryItem],
+        start_idx: usize,
+        pool_offset: Decimal,
+    ) -> Option<Decimal> {
+        if *self != CostBasisMethod::WeightedAverage {
+            return None;
+        }
+
+        let (total_amount, total_cost) = lots.iter().skip(start_idx).fold(
+            (Decimal::ZERO, Decimal::ZERO),
+            |(amount, cost), lot| {
+                (
+                    amount + lot.remaining_amount,
+                    cost + lot.remaining_amount * lot.cost_basis,
+                )
+            },
+        );
+
+        (!total_amount.is_zero()).then(|| (total_cost - pool_offset) / total_amount)
+    }
+}
+
+impl Display for CostBasisMethod {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fifo::LedgerSettings,
+        test_utils::{asset, dec, ledger, tx},
+        types::TransactionType::{Buying, Selling},
+    };
+
+    fn weighted_average() -> LedgerSettings {
+        LedgerSettings {
+            cost_basis_method: CostBasisMethod::WeightedAverage,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn weighted_average_keeps_cost_basis_of_acquisitions() {
+        let ledger = ledger(
+            vec![
+                tx(1, "2024-01-01", Buying, ("EUR", "100"), ("BTC", "1")),
+                tx(2, "2024-02-01", Buying, ("EUR", "300"), ("BTC", "1")),
+                tx(3, "2024-03-01", Selling, ("BTC", "1"), ("EUR", "250")),
+                tx(4, "2024-04-01", Selling, ("BTC", "0.5"), ("EUR", "150")),
+            ],
+            weighted_average(),
+        );
+        let items = ledger.in_order();
+
+        // Acquisitions keep their own cost basis.
+        assert_eq!(items[0].cost_basis(), dec("100"));
+        assert_eq!(items[1].cost_basis(), dec("300"));
+
+        // Disposals use the average of the pool, which isn't changed by the disposals.
+        assert_eq!(items[2].expense(), Some(dec("200")));
+        assert_eq!(items[2].profit(), Some(dec("50")));
+        assert_eq!(items[3].expense(), Some(dec("100")));
+
+        // Remaining half of the second lot is carried at the pool average.
+        let open_lots = ledger.open_lots(None).unwrap();
+        assert_eq!(open_lots.len(), 1);
+        assert_eq!(open_lots[0].asset, asset("BTC"));
+        assert_eq!(open_lots[0].ordinal, 2);
+        assert_eq!(open_lots[0].remaining_amount, dec("0.5"));
+        assert_eq!(open_lots[0].cost_basis, dec("200"));
+    }
+
+    #[test]
+    fn weighted_average_pool_includes_later_acquisitions() {
+        let ledger = ledger(
+            vec![
+                tx(1, "2024-01-01", Buying, ("EUR", "100"), ("BTC", "1")),
+                tx(2, "2024-02-01", Buying, ("EUR", "300"), ("BTC", "1")),
+                tx(3, "2024-03-01", Selling, ("BTC", "1"), ("EUR", "250")),
+                // Pool: 1 BTC at 200 & 1 BTC at 400 -> average of 300.
+                tx(4, "2024-04-01", Buying, ("EUR", "400"), ("BTC", "1")),
+                tx(5, "2024-05-01", Selling, ("BTC", "2"), ("EUR", "700")),
+            ],
+            weighted_average(),
+        );
+        let items = ledger.in_order();
+
+        assert_eq!(items[3].cost_basis(), dec("400"));
+        let expense: Decimal = items[4..].iter().filter_map(|item| item.expense()).sum();
+        assert_eq!(expense, dec("600"));
+        assert!(ledger.open_lots(None).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,34 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tax treatment of the crypto-to-crypto swaps.
+
+use serde::Deserialize;
+use std::fmt::{self, Display, Formatter};
+
+/// Method used to account for the swaps of one crypto asset for another.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SwapTreatment {
+    /// Swap isn't taxable, the acquired asset carries over the cost basis of the swapped asset.
+    #[default]
+    Carryover,
+    /// Swap is a disposal at the market value, which becomes the cost basis of the acquired asset.
+    #[serde(alias = "market_value")]
+    MarketValue,
+}
+
+impl Display for SwapTreatment {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
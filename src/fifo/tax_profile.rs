@@ -0,0 +1,50 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tax profiles, i.e. sets of jurisdiction specific rules applied on top of the ledger.
+
+use super::HoldingPeriodSettings;
+
+use serde::Deserialize;
+use std::fmt::{self, Display, Formatter};
+
+/// Jurisdiction specific rules applied on top of the ledger.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaxProfile {
+    /// German private sales (§23 EStG) & other income (§22 EStG) rules.
+    #[serde(alias = "de")]
+    Germany,
+    /// UK share pooling rules, replacing the FIFO breakdown & the yearly reports.
+    #[serde(alias = "uk")]
+    UnitedKingdom,
+}
+
+impl TaxProfile {
+    /// Holding period classification mandated by the profile, if any.
+    pub fn holding_period(&self) -> Option<HoldingPeriodSettings> {
+        match self {
+            // Private sales are tax-free if the asset was held for more than one year.
+            TaxProfile::Germany => Some(HoldingPeriodSettings {
+                long_term_months: 12,
+                long_term_exempt: true,
+            }),
+            TaxProfile::UnitedKingdom => None,
+        }
+    }
+}
+
+impl Display for TaxProfile {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
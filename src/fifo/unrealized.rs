@@ -0,0 +1,243 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Report of the unrealized gains & losses of the open lots, valued at the market price.
+
+use super::{Ledger, OpenLot};
+use crate::{
+    prices::PriceTable,
+    types::{AssetType, CsvField, LedgerError},
+};
+
+use chrono::NaiveDate;
+use itertools::Itertools;
+use rust_decimal::Decimal;
+use std::fmt::{self, Display, Formatter};
+
+/// Open lot valued at the market price.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UnrealizedLot {
+    /// The open lot.
+    pub lot: OpenLot,
+    /// Unit market price of the asset, `None` if it's not available.
+    pub price: Option<Decimal>,
+}
+
+impl UnrealizedLot {
+    /// Elements of the CSV header, matching the `csv_line_elements`.
+    pub const CSV_HEADER_ELEMENTS: [&'static str; 10] = [
+        "Asset",
+        "Account",
+        "Acquisition Date",
+        "Ordinal",
+        "Remaining Amount",
+        "Cost Basis",
+        "Total Cost",
+        "Price",
+        "Market Value",
+        "Unrealized Gain",
+    ];
+
+    /// Market value of the remaining amount.
+    pub fn market_value(&self) -> Option<Decimal> {
+        self.price.map(|price| price * self.lot.remaining_amount)
+    }
+
+    /// Unrealized gain (or loss, if negative) of the remaining amount.
+    pub fn unrealized_gain(&self) -> Option<Decimal> {
+        self.market_value()
+            .map(|market_value| market_value - self.lot.total_cost())
+    }
+
+    /// Elements of the single line in the CSV file.
+    pub fn csv_line_elements(&self) -> Vec<CsvField> {
+        let mut elements = self.lot.csv_line_elements();
+        elements.extend([
+            self.price.into(),
+            self.market_value().into(),
+            self.unrealized_gain().into(),
+        ]);
+        elements
+    }
+}
+
+/// Unrealized gain of all open lots of a single asset.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UnrealizedTotal {
+    /// Held asset.
+    pub asset: AssetType,
+    /// Total remaining amount.
+    pub amount: Decimal,
+    /// Total cost of the remaining amount.
+    pub total_cost: Decimal,
+    /// Unit market price of the asset, `None` if it's not available.
+    pub price: Option<Decimal>,
+}
+
+impl UnrealizedTotal {
+    /// Market value of the remaining amount.
+    pub fn market_value(&self) -> Option<Decimal> {
+        self.price.map(|price| price * self.amount)
+    }
+
+    /// Unrealized gain (or loss, if negative) of the remaining amount.
+    pub fn unrealized_gain(&self) -> Option<Decimal> {
+        self.market_value()
+            .map(|market_value| market_value - self.total_cost)
+    }
+}
+
+impl Display for UnrealizedTotal {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: Amount: {}, Total Cost: {:.2}",
+            self.asset, self.amount, self.total_cost
+        )?;
+
+        match (self.market_value(), self.unrealized_gain()) {
+            (Some(market_value), Some(gain)) => write!(
+                f,
+                ", Market Value: {market_value:.2}, Unrealized Gain: {gain:.2}"
+            ),
+            _ => write!(f, ", Market Value: N/A (price missing)"),
+        }
+    }
+}
+
+impl<'a> Ledger<'a> {
+    /// Open lots at the end of the given date, valued at the market price of that date.
+    ///
+    /// In case there's no price on the exact date, the most recent one before it is used.
+    /// Lots of assets without any price are still reported, but without the market value.
+    ///
+    /// # Returns
+    /// * `LedgerError` - In case the transactions up to the date can't be processed again.
+    pub fn unrealized_gains(
+        &'a self,
+        date: NaiveDate,
+        prices: &PriceTable,
+    ) -> Result<Vec<UnrealizedLot>, LedgerError> {
+        Ok(self
+            .open_lots(Some(date))?
+            .into_iter()
+            .map(|lot| UnrealizedLot {
+                price: prices.price(&lot.asset, date),
+                lot,
+            })
+            .collect())
+    }
+}
+
+/// Unrealized gains per asset, sorted by asset.
+pub fn unrealized_totals(lots: &[UnrealizedLot]) -> Vec<UnrealizedTotal> {
+    lots.iter()
+        .into_group_map_by(|lot| lot.lot.asset.clone())
+        .into_iter()
+        .sorted_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(asset, lots)| UnrealizedTotal {
+            asset,
+            amount: lots.iter().map(|lot| lot.lot.remaining_amount).sum(),
+            total_cost: lots.iter().map(|lot| lot.lot.total_cost()).sum(),
+            price: lots.first().and_then(|lot| lot.price),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fifo::LedgerSettings,
+        test_utils::{asset, date, dec, ledger, tx},
+        types::TransactionType::{Buying, Selling},
+    };
+
+    #[test]
+    fn open_lots_are_valued_at_the_latest_price() {
+        let ledger = ledger(
+            vec![
+                tx(1, "2024-01-01", Buying, ("EUR", "100"), ("BTC", "1")),
+                tx(2, "2024-02-01", Buying, ("EUR", "300"), ("BTC", "1")),
+                tx(3, "2024-03-01", Buying, ("EUR", "50"), ("DOGE", "500")),
+                tx(4, "2024-04-01", Selling, ("BTC", "1.5"), ("EUR", "450")),
+            ],
+            LedgerSettings::default(),
+        );
+        let mut prices = PriceTable::default();
+        prices.insert(asset("BTC"), date("2024-12-30"), dec("500"));
+        prices.insert(asset("BTC"), date("2025-01-02"), dec("900"));
+
+        let lots = ledger
+            .unrealized_gains(date("2024-12-31"), &prices)
+            .unwrap();
+        assert_eq!(lots.len(), 2);
+
+        // Only the rest of the partially consumed lot is valued.
+        let btc = &lots[0];
+        assert_eq!(btc.lot.ordinal, 2);
+        assert_eq!(btc.lot.remaining_amount, dec("0.5"));
+        assert_eq!(btc.price, Some(dec("500")));
+        assert_eq!(btc.market_value(), Some(dec("250")));
+        assert_eq!(btc.unrealized_gain(), Some(dec("100")));
+
+        // Lot without any price is reported without the market value.
+        let doge = &lots[1];
+        assert_eq!(doge.lot.asset, asset("DOGE"));
+        assert_eq!(doge.price, None);
+        assert_eq!(doge.market_value(), None);
+        assert_eq!(doge.unrealized_gain(), None);
+    }
+
+    #[test]
+    fn totals_are_summed_per_asset() {
+        let ledger = ledger(
+            vec![
+                tx(1, "2024-01-01", Buying, ("EUR", "100"), ("BTC", "1")),
+                tx(2, "2024-02-01", Buying, ("EUR", "300"), ("BTC", "1")),
+                tx(3, "2024-03-01", Buying, ("EUR", "50"), ("DOGE", "500")),
+                tx(4, "2024-04-01", Selling, ("BTC", "0.5"), ("EUR", "100")),
+            ],
+            LedgerSettings::default(),
+        );
+        let mut prices = PriceTable::default();
+        prices.insert(asset("BTC"), date("2024-06-01"), dec("250"));
+
+        let totals = unrealized_totals(
+            &ledger
+                .unrealized_gains(date("2024-06-30"), &prices)
+                .unwrap(),
+        );
+        assert_eq!(
+            totals,
+            vec![
+                UnrealizedTotal {
+                    asset: asset("BTC"),
+                    amount: dec("1.5"),
+                    total_cost: dec("350"),
+                    price: Some(dec("250")),
+                },
+                UnrealizedTotal {
+                    asset: asset("DOGE"),
+                    amount: dec("500"),
+                    total_cost: dec("50"),
+                    price: None,
+                },
+            ]
+        );
+        assert_eq!(totals[0].unrealized_gain(), Some(dec("25")));
+        assert_eq!(
+            totals[1].to_string(),
+            "DOGE: Amount: 500, Total Cost: 50.00, Market Value: N/A (price missing)"
+        );
+    }
+}
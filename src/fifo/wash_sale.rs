@@ -0,0 +1,399 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! US wash sale rule.
+//!
+//! Loss of a sale is disallowed if the same asset is acquired within the 30 days before or after it.
+//! Disallowed loss isn't lost, it's added to the cost basis of the replacement lots instead.
+//!
+//! Replacement lots are matched earliest first, and each lot can only replace as much as it holds.
+//! Lots consumed by the loss sale itself can't replace it.
+//! Since the replacement lots can be acquired after the sale, the rule is applied while processing the
+//! transactions, so the adjusted cost basis is used by all later disposals of the replacement lots.
+//!
+//! Only the matched part of the replacement lot is adjusted. In case the lot holds more,
+//! the matched part is split off into a lot of its own, so the rest of the lot keeps its cost basis.
+//! Holding period of the sold asset is tacked on to the holding period of the replacement.
+
+use super::{lineage::LotId, InventoryItem, Ledger};
+use crate::types::{HoldingKey, Transaction, TransactionType};
+
+use chrono::{Days, TimeDelta};
+use rust_decimal::Decimal;
+
+/// Number of days before & after the loss sale, in which the replacement lots are looked for.
+const WASH_SALE_WINDOW_DAYS: u64 = 30;
+
+/// Amount of an acquisition used as the replacement of a loss sale.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(super) struct Replacement {
+    /// Replacement amount.
+    amount: Decimal,
+    /// Disallowed loss to be added to the cost basis of the amount.
+    adjustment: Decimal,
+    /// Holding period of the sold asset, tacked on to the holding period of the replacement.
+    held_for: TimeDelta,
+}
+
+impl<'a> Ledger<'a> {
+    /// Apply the wash sale rule on a fragment of a sale, if it's a loss.
+    ///
+    /// # Arguments
+    /// * `item` - Fragment of the sale, not added to the ledger yet.
+    /// * `upcoming` - Transactions following the sale, in order.
+    pub(super) fn apply_wash_sale(&mut self, item: &mut InventoryItem, upcoming: &[Transaction]) {
+        let loss = match item.profit() {
+            Some(profit) if profit < Decimal::ZERO => -profit,
+            _ => return,
+        };
+
+        let window = Days::new(WASH_SALE_WINDOW_DAYS);
+        let (first_date, last_date) = (item.date - window, item.date + window);
+        let asset = item.input_type.clone();
+        let sold_amount = item.input_amount;
+        // Sale fragment is dated by the holding period of the sold lot.
+        let held_for = item.date - item.acquisition_date;
+
+        let mut unmatched_amount = sold_amount;
+        let mut disallowed_loss = Decimal::ZERO;
+
+        // 1. Lots acquired before the sale, which are still held.
+        let mut held_lots: Vec<(HoldingKey, usize)> = self
+            .ledger
+            .iter()
+            .filter(|(key, _)| key.asset == asset)
+            .flat_map(|(key, items)| {
+                items
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, lot)| {
+                        lot.held_since() >= first_date && lot.remaining_amount > lot.replaced_amount
+                    })
+                    .map(move |(idx, _)| (key.clone(), idx))
+            })
+            .collect();
+        held_lots.sort_by_key(|(key, idx)| {
+            let lot = &self.ledger[key][*idx];
+            (lot.held_since(), lot.ordinal)
+        });
+
+        let mut split_lots = Vec::new();
+        for (key, idx) in held_lots {
+            if unmatched_amount.is_zero() {
+                break;
+            }
+
+            let lot = &self.ledger[&key][idx];
+            let matched = unmatched_amount.min(lot.remaining_amount - lot.replaced_amount);
+            let replacement = Replacement {
+                amount: matched,
+                adjustment: loss * matched / sold_amount,
+                held_for,
+            };
+            let split_lot_id = (matched != lot.output_amount).then(|| self.next_lot_id());
+
+            let lot = &mut self.ledger.get_mut(&key).expect("Key was just found.")[idx];
+            if let Some(split_lot) = replacement.apply(lot, split_lot_id) {
+                split_lots.push((key, idx, split_lot));
+            }
+
+            unmatched_amount -= matched;
+            disallowed_loss += replacement.adjustment;
+        }
+
+        // Split off lots follow the lots they were split from, so the consumption order is preserved.
+        split_lots.sort_by_key(|(_, idx, _)| *idx);
+        for (key, idx, split_lot) in split_lots.into_iter().rev() {
+            self.ledger
+                .get_mut(&key)
+                .expect("Key was just found.")
+                .insert(idx + 1, split_lot);
+        }
+
+        // 2. Acquisitions made after the sale, adjusted once they're processed.
+        for transaction in upcoming
+            .iter()
+            .take_while(|transaction| transaction.date() <= last_date)
+        {
+            if unmatched_amount.is_zero() {
+                break;
+            }
+
+            let (output_token, output_amount) = transaction.output();
+            if transaction.tx_type() == TransactionType::Transfer || output_token != asset {
+                continue;
+            }
+
+            let pending = self
+                .pending_replacements
+                .entry(transaction.ordinal())
+                .or_default();
+            let available = output_amount - pending.iter().map(|part| part.amount).sum::<Decimal>();
+            if available <= Decimal::ZERO {
+                continue;
+            }
+
+            let matched = unmatched_amount.min(available);
+            let adjustment = loss * matched / sold_amount;
+            pending.push(Replacement {
+                amount: matched,
+                adjustment,
+                held_for,
+            });
+
+            unmatched_amount -= matched;
+            disallowed_loss += adjustment;
+        }
+
+        if !disallowed_loss.is_zero() {
+            item.disallowed_loss = Some(disallowed_loss);
+        }
+    }
+
+    /// Add the disallowed losses of the earlier sales to the cost basis of the newly acquired lots.
+    ///
+    /// Replacements are matched with the lots in order, splitting the lots which hold more than the replacement.
+    ///
+    /// # Arguments
+    /// * `transaction` - Transaction which acquired the lots.
+    /// * `items` - Lots acquired by the transaction, not added to the ledger yet.
+    pub(super) fn apply_pending_replacement(
+        &mut self,
+        transaction: &Transaction,
+        items: &mut Vec<InventoryItem>,
+    ) {
+        let Some(replacements) = self.pending_replacements.remove(&transaction.ordinal()) else {
+            return;
+        };
+
+        let mut idx = 0;
+        for mut replacement in replacements {
+            while !replacement.amount.is_zero() && idx < items.len() {
+                let lot = &items[idx];
+                let available = lot.remaining_amount - lot.replaced_amount;
+                if available.is_zero() {
+                    idx += 1;
+                    continue;
+                }
+
+                // Replacement spanning several lots is split between them proportionally.
+                let amount = replacement.amount.min(available);
+                let adjustment = if amount == replacement.amount {
+                    replacement.adjustment
+                } else {
+                    replacement.adjustment * amount / replacement.amount
+                };
+                replacement.amount -= amount;
+                replacement.adjustment -= adjustment;
+                let part = Replacement {
+                    amount,
+                    adjustment,
+                    held_for: replacement.held_for,
+                };
+
+                let split_lot_id = (part.amount != lot.output_amount).then(|| self.next_lot_id());
+                if let Some(split_lot) = part.apply(&mut items[idx], split_lot_id) {
+                    items.insert(idx + 1, split_lot);
+                }
+            }
+        }
+    }
+}
+
+impl Replacement {
+    /// Apply the replacement on the lot.
+    ///
+    /// If the lot holds more than the replacement amount, the amount is split off into a new lot with the given Id,
+    /// which is then adjusted & returned. Otherwise the lot itself is adjusted.
+    fn apply(&self, lot: &mut InventoryItem, split_lot_id: Option<LotId>) -> Option<InventoryItem> {
+        let mut split_lot = split_lot_id.map(|lot_id| split_off(lot, self.amount, lot_id));
+        let replacement_lot = split_lot.as_mut().unwrap_or(lot);
+
+        replacement_lot.cost_basis += self.adjustment / self.amount;
+        replacement_lot.replaced_amount = self.amount;
+        replacement_lot.wash_sale_adjustment = Some(self.adjustment);
+        replacement_lot.holding_period_start = Some(replacement_lot.held_since() - self.held_for);
+
+        split_lot
+    }
+}
+
+/// Split the `amount` off the remaining amount of the lot, into a new lot of the same transaction.
+///
+/// Amounts & values of the lot are split proportionally, so both lots together still match the transaction.
+fn split_off(lot: &mut InventoryItem, amount: Decimal, lot_id: LotId) -> InventoryItem {
+    let share = amount / lot.output_amount;
+    let mut split_part = |value: &mut Decimal| {
+        let part = *value * share;
+        *value -= part;
+        part
+    };
+
+    let mut split_lot = lot.clone();
+    split_lot.lot_id = lot_id;
+    split_lot.input_amount = split_part(&mut lot.input_amount);
+    split_lot.fee = lot.fee.as_mut().map(&mut split_part);
+    split_lot.disallowed_loss = lot.disallowed_loss.as_mut().map(&mut split_part);
+    split_lot.output_amount = amount;
+    split_lot.remaining_amount = amount;
+
+    lot.output_amount -= amount;
+    lot.remaining_amount -= amount;
+    split_lot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fifo::{LedgerSettings, SwapTreatment},
+        prices::PriceTable,
+        test_utils::{asset, date, dec, ledger, tx},
+        types::TransactionType::{Buying, Selling, Swap},
+    };
+
+    fn wash_sales() -> LedgerSettings {
+        LedgerSettings {
+            wash_sales: true,
+            ..Default::default()
+        }
+    }
+
+    /// Sale fragments of the transaction.
+    fn sales<'l>(ledger: &'l Ledger, ordinal: u32) -> Vec<&'l InventoryItem> {
+        ledger
+            .ledger
+            .values()
+            .flatten()
+            .filter(|item| item.ordinal == ordinal && item.sale_price().is_some())
+            .collect()
+    }
+
+    /// Lots of the asset, in the consumption order.
+    fn lots<'l>(ledger: &'l Ledger, name: &str) -> &'l [InventoryItem] {
+        &ledger.ledger[&HoldingKey::new(asset(name), None, false)]
+    }
+
+    #[test]
+    fn partial_replacement_bought_before_the_sale() {
+        let ledger = ledger(
+            vec![
+                tx(1, "2024-01-01", Buying, ("EUR", "200"), ("BTC", "2")),
+                tx(2, "2024-02-20", Buying, ("EUR", "150"), ("BTC", "1")),
+                tx(3, "2024-03-01", Selling, ("BTC", "0.5"), ("EUR", "20")),
+            ],
+            wash_sales(),
+        );
+
+        let sale = sales(&ledger, 3)[0];
+        assert_eq!(sale.disallowed_loss(), Some(dec("30")));
+        assert_eq!(sale.profit(), Some(Decimal::ZERO));
+
+        let btc = lots(&ledger, "BTC");
+        assert_eq!(btc.len(), 3);
+        // Rest of the replacement lot keeps its cost basis.
+        assert_eq!(btc[1].ordinal, 2);
+        assert_eq!(btc[1].remaining_amount, dec("0.5"));
+        assert_eq!(btc[1].cost_basis, dec("150"));
+        assert_eq!(btc[1].wash_sale_adjustment, None);
+        assert_eq!(btc[1].held_since(), date("2024-02-20"));
+
+        // Matched part is split off, carrying the whole adjustment & the holding period of the sold lot.
+        let replacement = &btc[2];
+        assert_eq!(replacement.ordinal, 2);
+        assert_eq!(replacement.input(), (&asset("EUR"), dec("75")));
+        assert_eq!(replacement.remaining_amount, dec("0.5"));
+        assert_eq!(replacement.replaced_amount, dec("0.5"));
+        assert_eq!(replacement.cost_basis, dec("210"));
+        assert_eq!(replacement.wash_sale_adjustment, Some(dec("30")));
+        assert_eq!(replacement.held_since(), date("2023-12-22"));
+    }
+
+    #[test]
+    fn partial_replacement_bought_after_the_sale() {
+        let ledger = ledger(
+            vec![
+                tx(1, "2024-01-01", Buying, ("EUR", "100"), ("BTC", "1")),
+                tx(2, "2024-03-01", Selling, ("BTC", "1"), ("EUR", "60")),
+                tx(3, "2024-03-15", Buying, ("EUR", "140"), ("BTC", "2")),
+                // Consumes the rest of the acquisition, and half of the replacement.
+                tx(4, "2024-06-01", Selling, ("BTC", "1.5"), ("EUR", "300")),
+            ],
+            wash_sales(),
+        );
+
+        let sale = sales(&ledger, 2)[0];
+        assert_eq!(sale.disallowed_loss(), Some(dec("40")));
+
+        let btc = lots(&ledger, "BTC");
+        assert_eq!(btc.len(), 3);
+        assert_eq!(btc[1].output(), (&asset("BTC"), dec("1")));
+        assert_eq!(btc[1].cost_basis, dec("70"));
+
+        let replacement = &btc[2];
+        assert_eq!(replacement.output(), (&asset("BTC"), dec("1")));
+        assert_eq!(replacement.cost_basis, dec("110"));
+        assert_eq!(replacement.held_since(), date("2024-01-15"));
+        // Replaced amount shrinks with the consumed amount.
+        assert_eq!(replacement.remaining_amount, dec("0.5"));
+        assert_eq!(replacement.replaced_amount, dec("0.5"));
+
+        let expense: Decimal = sales(&ledger, 4)
+            .iter()
+            .filter_map(|item| item.expense())
+            .sum();
+        assert_eq!(expense, dec("125"));
+    }
+
+    #[test]
+    fn replacement_lot_is_used_only_once() {
+        let ledger = ledger(
+            vec![
+                tx(1, "2024-01-01", Buying, ("EUR", "200"), ("BTC", "2")),
+                tx(2, "2024-02-20", Buying, ("EUR", "100"), ("BTC", "1")),
+                tx(3, "2024-03-01", Selling, ("BTC", "1"), ("EUR", "50")),
+                tx(4, "2024-03-02", Selling, ("BTC", "1"), ("EUR", "50")),
+            ],
+            wash_sales(),
+        );
+
+        // Only the first loss sale has a replacement.
+        assert_eq!(sales(&ledger, 3)[0].disallowed_loss(), Some(dec("50")));
+        let second_sale = sales(&ledger, 4)[0];
+        assert_eq!(second_sale.disallowed_loss(), None);
+        assert_eq!(second_sale.profit(), Some(dec("-50")));
+    }
+
+    #[test]
+    fn market_value_swap_loss_is_a_wash_sale() {
+        let mut prices = PriceTable::default();
+        prices.insert(asset("ETH"), date("2024-02-01"), dec("6"));
+        let transactions = vec![
+            tx(1, "2024-01-01", Buying, ("EUR", "100"), ("BTC", "1")),
+            tx(2, "2024-02-01", Swap, ("BTC", "1"), ("ETH", "10")),
+            tx(3, "2024-02-10", Buying, ("EUR", "70"), ("BTC", "1")),
+        ];
+        let settings = LedgerSettings {
+            swap_treatment: SwapTreatment::MarketValue,
+            ..wash_sales()
+        };
+        let ledger = Ledger::new(transactions, settings, Some(&prices)).unwrap();
+
+        let swap = sales(&ledger, 2)[0];
+        assert_eq!(swap.disallowed_loss(), Some(dec("40")));
+        assert_eq!(swap.profit(), Some(Decimal::ZERO));
+
+        let replacement = &lots(&ledger, "BTC")[1];
+        assert_eq!(replacement.cost_basis, dec("110"));
+        assert_eq!(replacement.held_since(), date("2024-01-10"));
+    }
+}
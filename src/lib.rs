@@ -0,0 +1,65 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Engine for calculating the capital gains of crypto assets, using the FIFO (or another) cost basis method.
+//!
+//! The processing pipeline consists of the following steps:
+//!
+//! 1. [`parser`] - parse the transactions from the configured data sources (XLSX & CSV).
+//! 2. [`types::TransactionsProvider`] - collect the transactions, convert them into the base currency & sort them.
+//! 3. [`validation`] - validate the transactions in context, e.g. that no balance goes negative.
+//! 4. [`fifo::Ledger`] - process the transactions & calculate the cost basis of each disposal.
+//! 5. Report - yearly income/loss report, and the CSV breakdown via [`types::CsvHelper`], the XLSX report via [`report::XlsxReport`]
+//!    or the JSON export via [`report::JsonReport`].
+//!
+//! The UK share pooling rules are implemented separately by [`uk::UkLedger`], as an alternative to step 4.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use fifo_accounting::{fifo::Ledger, parser::SourceParser, types::TransactionsProvider, validation, Config};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let config: Config = toml::from_str(&std::fs::read_to_string("Config.toml")?)?;
+//! config.ledger.validate()?;
+//!
+//! let provider = TransactionsProvider::new(SourceParser::new(config.entries), config.ledger.fiat.clone());
+//! let transactions = provider.get()?;
+//! validation::context_validation(
+//!     &transactions,
+//!     &config.ledger.fiat,
+//!     config.ledger.per_account_inventory,
+//!     false,
+//! )?;
+//!
+//! // Prices are only required if the swaps are taxed at the market value.
+//! let ledger = Ledger::new(transactions, config.ledger, None)?;
+//! for report in ledger.yearly_income_loss_report() {
+//!     println!("{report}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod config;
+pub mod fifo;
+pub mod parser;
+pub mod prices;
+pub mod report;
+pub mod types;
+pub mod uk;
+pub mod validation;
+
+#[cfg(test)]
+mod test_utils;
+
+pub use config::Config;
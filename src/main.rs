@@ -10,18 +10,22 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-mod fifo;
-mod parser;
-mod types;
-mod validation;
+use fifo_accounting::{
+    fifo,
+    parser::SourceParser,
+    prices::PriceTable,
+    report::{JsonReport, XlsxReport},
+    types::{
+        CsvField, CsvFormat, CsvHelper, CsvWriter, FiatSettings, FiscalYear, Transaction,
+        TransactionsProvider,
+    },
+    uk, validation, Config,
+};
 
-use crate::parser::xlsx_parser::{XlsxFileEntry, XlsxParser};
-use types::{CsvHelper, TransactionsProvider};
-
-use clap::Parser;
+use chrono::NaiveDate;
+use clap::{Parser, ValueEnum};
 use env_logger::Env;
-use serde::Deserialize;
-use std::collections::HashSet;
+use std::{collections::HashSet, fs::File, io::BufWriter, process::ExitCode};
 
 /// Command-line arguments
 #[derive(Debug, Parser)]
@@ -30,12 +34,70 @@ struct CmdArgs {
     #[clap(short, long, default_value = "Config.toml")]
     config_path: String,
 
-    /// Path to the FIFO output file
-    #[clap(short, long, default_value = "fifo_output.csv")]
-    fifo_output: String,
+    /// Path to the FIFO output file [default: fifo_output.<format>]
+    #[clap(short, long)]
+    fifo_output: Option<String>,
+
+    /// Format of the FIFO output file
+    #[clap(long, value_enum, default_value_t = OutputFormat::Csv)]
+    format: OutputFormat,
+
+    /// Validate all transactions and report all errors, instead of stopping at the first invalid one
+    #[clap(long)]
+    all_errors: bool,
+
+    /// Path to the open lots (remaining inventory) output file, not written if omitted
+    #[clap(long)]
+    open_lots: Option<String>,
+
+    /// Cutoff date (YYYY-MM-DD) of the open lots report, e.g. the year end [default: after all transactions]
+    #[clap(long)]
+    open_lots_date: Option<NaiveDate>,
+
+    /// Path to the unrealized gains output file, not written if omitted. Requires the `prices` file in the config
+    #[clap(long)]
+    unrealized_gains: Option<String>,
+
+    /// Reporting date (YYYY-MM-DD) of the unrealized gains report [default: today]
+    #[clap(long)]
+    unrealized_gains_date: Option<NaiveDate>,
+}
+
+impl CmdArgs {
+    /// Path to the FIFO output file, with the extension of the output format by default.
+    fn fifo_output_path(&self) -> String {
+        self.fifo_output
+            .clone()
+            .unwrap_or_else(|| format!("fifo_output.{}", self.format.extension()))
+    }
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// Format of the FIFO output file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Delimiter-separated breakdown, using the configured `csv_*` format.
+    Csv,
+    /// Workbook with one sheet per section: breakdown, yearly summaries, open lots & per-asset totals.
+    Xlsx,
+    /// Single JSON document with the transactions, inventory items & yearly reports.
+    Json,
+    /// Newline-delimited JSON, one transaction, inventory item or yearly report per line.
+    Ndjson,
+}
+
+impl OutputFormat {
+    /// Extension of the output files in this format.
+    fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Csv => "csv",
+            OutputFormat::Xlsx => "xlsx",
+            OutputFormat::Json => "json",
+            OutputFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+fn main() -> ExitCode {
     env_logger::Builder::from_env(Env::default().default_filter_or("info"))
         .format(|buf, record| {
             use std::io::Write;
@@ -49,21 +111,58 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         })
         .init();
 
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            log::error!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Parse the transactions, process them in the ledger & write the report.
+fn run() -> Result<(), Box<dyn std::error::Error>> {
     // 0. Parse the config file
     let cmd_args = CmdArgs::parse();
-    let toml_content = std::fs::read_to_string(cmd_args.config_path)?;
+    let toml_content = std::fs::read_to_string(&cmd_args.config_path)?;
     let config: Config = toml::from_str(&toml_content)?;
 
+    let exchange_rates = config
+        .ledger
+        .fiat
+        .exchange_rates
+        .as_deref()
+        .map(PriceTable::from_file)
+        .transpose()?;
+    let prices = config
+        .prices
+        .as_deref()
+        .map(PriceTable::from_file)
+        .transpose()?;
+    config.ledger.validate()?;
+
     log::info!("Configuration files loaded successfully.");
 
-    // 1. Parse the XLSX files and validate the data.
+    // 1. Parse the XLSX & CSV files and validate the data.
     // NOTE: If user wants to have different data source, they should modify the line below with their own implementation.
-    // The `XlsxParser` should be replaced with a custom type that implements the Iterator<Item = ParserDataType> trait.
-    let tx_provider: TransactionsProvider<_> = XlsxParser::new(config.entries).into();
+    // The `SourceParser` should be replaced with a custom type that implements the Iterator<Item = ParserDataType> trait.
+    let mut tx_provider = TransactionsProvider::new(
+        SourceParser::new(config.entries),
+        config.ledger.fiat.clone(),
+    )
+    .with_tax_timezone(config.tax_timezone);
+    if let Some(exchange_rates) = exchange_rates {
+        tx_provider = tx_provider.with_exchange_rates(exchange_rates);
+    }
     let transactions = tx_provider.get()?;
     log::info!("Finished parsing all transactions.");
 
-    let final_asset_state = validation::context_validation(&transactions)?;
+    let final_asset_state = validation::context_validation(
+        &transactions,
+        &config.ledger.fiat,
+        config.ledger.per_account_inventory,
+        cmd_args.all_errors,
+    )?;
     log::info!("Contextual validation completed successfully.");
     log::debug!("Final asset state: {:#?}", final_asset_state);
 
@@ -74,42 +173,250 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .collect::<HashSet<_>>();
     log::info!("Parsed following unique asset types: {:?}", asset_types);
 
-    // 3. Create the ledger & process the transactions in FIFO manner.
-    let ledger = fifo::Ledger::new(transactions);
+    // 3. Process the transactions, either by the UK share pooling rules or in the ledger.
+    if config.ledger.tax_profile == Some(fifo::TaxProfile::UnitedKingdom) {
+        write_uk_reports(
+            &cmd_args,
+            &config.csv,
+            &config.ledger.fiat,
+            &transactions,
+            prices.as_ref(),
+        )?;
+    } else {
+        write_ledger_reports(
+            &cmd_args,
+            &config.csv,
+            config.ledger,
+            transactions,
+            prices.as_ref(),
+        )?;
+    }
+
+    log::info!("Thank you so much for using this program!");
+    log::info!("And remember:");
+    log::info!("{}", GREETING);
+    Ok(())
+}
+
+/// Match the disposals by the UK share pooling rules & write the reports.
+///
+/// UK share pooling replaces the FIFO breakdown, the yearly reports & the open lots.
+fn write_uk_reports(
+    cmd_args: &CmdArgs,
+    csv_format: &CsvFormat,
+    fiat: &FiatSettings,
+    transactions: &[Transaction],
+    prices: Option<&PriceTable>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if matches!(cmd_args.format, OutputFormat::Json | OutputFormat::Ndjson) {
+        return Err(
+            "JSON output isn't supported with the UK share pooling, use the CSV or XLSX format instead."
+                .into(),
+        );
+    }
+    if cmd_args.unrealized_gains.is_some() {
+        return Err("Unrealized gains report isn't supported with the UK share pooling.".into());
+    }
+
+    let uk_ledger = uk::UkLedger::new(transactions, fiat, prices)?;
+    let fifo_output = cmd_args.fifo_output_path();
+
+    let tax_year_reports = uk_ledger.tax_year_reports();
+    log::info!("UK tax year reports:");
+    tax_year_reports
+        .iter()
+        .for_each(|report| log::info!("{}", report));
+
+    // 4. Generate the output file.
+    if cmd_args.format == OutputFormat::Xlsx {
+        let mut xlsx_report = XlsxReport::new();
+        xlsx_report.add_uk_disposals(uk_ledger.disposals())?;
+        xlsx_report.add_uk_tax_years(&tax_year_reports)?;
+        xlsx_report.add_uk_pools(uk_ledger.holdings())?;
+        xlsx_report.save(&fifo_output)?;
+        log::info!("XLSX report written to file: {}", fifo_output);
+    } else {
+        write_csv(
+            &fifo_output,
+            csv_format,
+            &uk::UkDisposal::CSV_HEADER_ELEMENTS,
+            uk_ledger
+                .disposals()
+                .iter()
+                .map(|disposal| disposal.csv_line_elements()),
+        )?;
+        log::info!(
+            "UK share pooling breakdown written to file: {}",
+            fifo_output
+        );
+    }
+
+    // 5. Generate the Section 104 pools report, if requested.
+    if let Some(pools_output) = &cmd_args.open_lots {
+        // Pools at the cutoff date only consider the transactions made until then.
+        let cutoff_ledger = match cmd_args.open_lots_date {
+            Some(date) => {
+                log::info!("Section 104 pools at the end of {date}:");
+                Some(uk::UkLedger::new_until(transactions, date, fiat, prices)?)
+            }
+            None => {
+                log::info!("Section 104 pools after all transactions:");
+                None
+            }
+        };
+        let holdings = cutoff_ledger.as_ref().unwrap_or(&uk_ledger).holdings();
+
+        let lines: Vec<_> = holdings
+            .inspect(|(asset, pool)| {
+                log::info!(
+                    "{}: Amount: {}, Allowable Cost: {:.2}",
+                    asset,
+                    pool.amount,
+                    pool.cost
+                )
+            })
+            .map(|(asset, pool)| pool.csv_line_elements(asset))
+            .collect();
+        write_csv(
+            pools_output,
+            csv_format,
+            &uk::Section104Pool::CSV_HEADER_ELEMENTS,
+            lines.into_iter(),
+        )?;
+        log::info!("Section 104 pools written to file: {}", pools_output);
+    }
+
+    Ok(())
+}
+
+/// Process the transactions in the ledger & write the reports.
+fn write_ledger_reports(
+    cmd_args: &CmdArgs,
+    csv_format: &CsvFormat,
+    settings: fifo::LedgerSettings,
+    transactions: Vec<Transaction>,
+    prices: Option<&PriceTable>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("Using cost basis method: {}", settings.cost_basis_method);
+    if settings.fiscal_year != FiscalYear::CALENDAR {
+        log::info!(
+            "Using fiscal year starting on: {} (MM-DD)",
+            settings.fiscal_year
+        );
+    }
+    if settings.swap_treatment == fifo::SwapTreatment::MarketValue {
+        log::info!("Swaps are taxed at the market value.");
+    }
+    let tax_profile = settings.tax_profile;
+    let ledger = fifo::Ledger::new(transactions, settings, prices)?;
 
+    let yearly_reports = ledger.yearly_reports();
     log::info!("Yearly income/loss reports:");
-    ledger
-        .yearly_income_loss_report()
+    yearly_reports
         .iter()
         .for_each(|report| log::info!("{}", report));
 
-    // 4. Generate the output CSV file.
-    let csv_helper = CsvHelper::new(config.csv_delimiter.clone());
-    let lines = ledger
-        .csv_line_iter()
-        .map(|line| csv_helper.to_csv_line(line))
-        .collect::<Vec<_>>();
-
-    // Write the output to a file.
-    std::fs::write(
-        &cmd_args.fifo_output,
-        format!("{}\n{}", csv_helper.csv_header(), lines.join("\n")),
-    )
-    .unwrap();
-    log::info!("FIFO breakdown written to file: {}", cmd_args.fifo_output);
+    if tax_profile == Some(fifo::TaxProfile::Germany) {
+        log::info!("Yearly German tax reports (§23 & §22 EStG):");
+        ledger
+            .german_tax_report()
+            .iter()
+            .for_each(|report| log::info!("{}", report));
+    }
+
+    // 4. Generate the output file.
+    let fifo_output = cmd_args.fifo_output_path();
+    match cmd_args.format {
+        OutputFormat::Xlsx => {
+            let open_lots = ledger.open_lots(cmd_args.open_lots_date)?;
+            let mut xlsx_report = XlsxReport::new();
+            xlsx_report.add_fifo_breakdown(ledger.csv_line_iter())?;
+            xlsx_report.add_yearly_reports(&yearly_reports)?;
+            xlsx_report.add_open_lots(&open_lots)?;
+            xlsx_report.add_asset_totals(&fifo::open_lots_totals(&open_lots))?;
+            xlsx_report.save(&fifo_output)?;
+            log::info!("XLSX report written to file: {}", fifo_output);
+        }
+        OutputFormat::Json | OutputFormat::Ndjson => {
+            let json_report = JsonReport::new(&ledger);
+            let writer = BufWriter::new(File::create(&fifo_output)?);
+            if cmd_args.format == OutputFormat::Ndjson {
+                json_report.write_ndjson(writer)?;
+            } else {
+                json_report.write_json(writer)?;
+            }
+            log::info!("Ledger state written to file: {}", fifo_output);
+        }
+        OutputFormat::Csv => {
+            let csv_helper = CsvHelper::new(csv_format.clone());
+            csv_helper.write(
+                BufWriter::new(File::create(&fifo_output)?),
+                ledger.csv_line_iter(),
+            )?;
+            log::info!("FIFO breakdown written to file: {}", fifo_output);
+        }
+    }
+
+    // 5. Generate the open lots report, if requested.
+    if let Some(open_lots_output) = &cmd_args.open_lots {
+        let open_lots = ledger.open_lots(cmd_args.open_lots_date)?;
+
+        match cmd_args.open_lots_date {
+            Some(date) => log::info!("Open lots at the end of {date}:"),
+            None => log::info!("Open lots after all transactions:"),
+        }
+        fifo::open_lots_totals(&open_lots)
+            .iter()
+            .for_each(|total| log::info!("{}", total));
+
+        write_csv(
+            open_lots_output,
+            csv_format,
+            &fifo::OpenLot::CSV_HEADER_ELEMENTS,
+            open_lots.iter().map(|lot| lot.csv_line_elements()),
+        )?;
+        log::info!("Open lots written to file: {}", open_lots_output);
+    }
+
+    // 6. Generate the unrealized gains report, if requested.
+    if let Some(unrealized_output) = &cmd_args.unrealized_gains {
+        let prices = prices
+            .ok_or("Unrealized gains report requires the `prices` file to be set in the config.")?;
+        let date = cmd_args
+            .unrealized_gains_date
+            .unwrap_or_else(|| chrono::Local::now().date_naive());
+        let unrealized_lots = ledger.unrealized_gains(date, prices)?;
+
+        log::info!("Unrealized gains at the end of {date}:");
+        fifo::unrealized_totals(&unrealized_lots)
+            .iter()
+            .for_each(|total| log::info!("{}", total));
+
+        write_csv(
+            unrealized_output,
+            csv_format,
+            &fifo::UnrealizedLot::CSV_HEADER_ELEMENTS,
+            unrealized_lots.iter().map(|lot| lot.csv_line_elements()),
+        )?;
+        log::info!("Unrealized gains written to file: {}", unrealized_output);
+    }
 
-    log::info!("Thank you so much for using this program!");
-    log::info!("And remember:");
-    log::info!("{}", GREETING);
     Ok(())
 }
 
-#[derive(Debug, Deserialize)]
-struct Config {
-    /// Separator to use in the output CSV file.
-    csv_delimiter: String,
-    /// List of entries to parse.
-    entries: Vec<XlsxFileEntry>,
+/// Write the header & the lines to the CSV file at the given path.
+fn write_csv(
+    path: &str,
+    format: &CsvFormat,
+    header: &[&str],
+    lines: impl Iterator<Item = Vec<CsvField>>,
+) -> Result<(), csv::Error> {
+    let mut writer = CsvWriter::from_path(path, format)?;
+    writer.write_record(header.iter().copied())?;
+    for line in lines {
+        writer.write_record(line)?;
+    }
+    writer.into_inner().map(|_| ())
 }
 
 const GREETING: &str = r#"
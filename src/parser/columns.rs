@@ -0,0 +1,367 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::Deserialize;
+
+use crate::types::ColumnError;
+
+/// Reference to a column in the input data.
+///
+/// In the config file, header name is a plain string (e.g. `"Date"`),
+/// while the column letter is a table (e.g. `{ column = "B" }`).
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum ColumnRef {
+    /// Name of the column header.
+    Header(String),
+    /// Column letter(s), e.g. `A` or `AB`.
+    Letter { column: String },
+}
+
+impl ColumnRef {
+    fn header(name: &str) -> Self {
+        ColumnRef::Header(name.to_string())
+    }
+
+    fn letter(column: &str) -> Self {
+        ColumnRef::Letter {
+            column: column.to_string(),
+        }
+    }
+
+    /// Whether the column is referenced by the header name.
+    pub fn is_header(&self) -> bool {
+        matches!(self, ColumnRef::Header(_))
+    }
+}
+
+/// Mapping of the logical transaction fields to the columns in the input data.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ColumnMapping {
+    /// Ordinal number of the transaction. If not mapped, the row number is used instead.
+    #[serde(default)]
+    ordinal: Option<ColumnRef>,
+    /// Date of the transaction.
+    #[serde(default = "ColumnMapping::default_date")]
+    date: ColumnRef,
+    /// Type of the transaction.
+    #[serde(default = "ColumnMapping::default_tx_type")]
+    tx_type: ColumnRef,
+    /// Input token.
+    #[serde(default = "ColumnMapping::default_input_type")]
+    input_type: ColumnRef,
+    /// Input amount.
+    #[serde(default = "ColumnMapping::default_input_amount")]
+    input_amount: ColumnRef,
+    /// Output token.
+    #[serde(default = "ColumnMapping::default_output_type")]
+    output_type: ColumnRef,
+    /// Output amount.
+    #[serde(default = "ColumnMapping::default_output_amount")]
+    output_amount: ColumnRef,
+    /// Account (wallet or exchange) of the transaction, optional.
+    #[serde(default)]
+    account: Option<ColumnRef>,
+    /// Account receiving the assets of a transfer, optional.
+    #[serde(default)]
+    target_account: Option<ColumnRef>,
+    /// Fee token, optional.
+    #[serde(default)]
+    fee_type: Option<ColumnRef>,
+    /// Fee amount, optional. Required if the fee token is mapped.
+    #[serde(default)]
+    fee_amount: Option<ColumnRef>,
+}
+
+impl ColumnMapping {
+    fn default_date() -> ColumnRef {
+        ColumnRef::header("Date")
+    }
+
+    fn default_tx_type() -> ColumnRef {
+        ColumnRef::header("Transaction Type")
+    }
+
+    fn default_input_type() -> ColumnRef {
+        ColumnRef::header("Input Token")
+    }
+
+    fn default_input_amount() -> ColumnRef {
+        ColumnRef::header("Input Amount")
+    }
+
+    fn default_output_type() -> ColumnRef {
+        ColumnRef::header("Output Token")
+    }
+
+    fn default_output_amount() -> ColumnRef {
+        ColumnRef::header("Output Amount")
+    }
+
+    /// Mapping of the fixed column layout, with all columns in the default order (`A` to `G`).
+    /// Optional columns are provided as column letters.
+    pub fn positional(
+        account: Option<&str>,
+        target_account: Option<&str>,
+        fee_type: Option<&str>,
+        fee_amount: Option<&str>,
+    ) -> Self {
+        ColumnMapping {
+            ordinal: Some(ColumnRef::letter("A")),
+            date: ColumnRef::letter("B"),
+            tx_type: ColumnRef::letter("C"),
+            input_type: ColumnRef::letter("D"),
+            input_amount: ColumnRef::letter("E"),
+            output_type: ColumnRef::letter("F"),
+            output_amount: ColumnRef::letter("G"),
+            account: account.map(ColumnRef::letter),
+            target_account: target_account.map(ColumnRef::letter),
+            fee_type: fee_type.map(ColumnRef::letter),
+            fee_amount: fee_amount.map(ColumnRef::letter),
+        }
+    }
+
+    /// Whether any of the columns is referenced by the header name.
+    pub fn uses_headers(&self) -> bool {
+        [&self.date, &self.tx_type, &self.input_type]
+            .into_iter()
+            .chain([&self.input_amount, &self.output_type, &self.output_amount])
+            .chain(self.ordinal.iter())
+            .chain(self.account.iter())
+            .chain(self.target_account.iter())
+            .chain(self.fee_type.iter())
+            .chain(self.fee_amount.iter())
+            .any(ColumnRef::is_header)
+    }
+
+    /// Resolve the mapping into column indices, using the provided header row.
+    ///
+    /// Header names are matched case-insensitively, ignoring the surrounding whitespace.
+    /// In case any of the mapped headers is missing, an error naming all of the missing headers is returned.
+    pub fn resolve<S: AsRef<str>>(&self, headers: &[S]) -> Result<ColumnIndices, ColumnError> {
+        if self.fee_type.is_some() != self.fee_amount.is_some() {
+            return Err(ColumnError::IncompleteFee);
+        }
+
+        let mut missing = Vec::new();
+        let mut invalid = Vec::new();
+
+        let mut find = |column: &ColumnRef| match column {
+            ColumnRef::Header(name) => headers
+                .iter()
+                .position(|header| header.as_ref().trim().eq_ignore_ascii_case(name.trim()))
+                .unwrap_or_else(|| {
+                    missing.push(name.to_string());
+                    0
+                }),
+            ColumnRef::Letter { column } => column_index(column).unwrap_or_else(|| {
+                invalid.push(column.to_string());
+                0
+            }),
+        };
+
+        let indices = ColumnIndices {
+            ordinal: self.ordinal.as_ref().map(&mut find),
+            date: find(&self.date),
+            tx_type: find(&self.tx_type),
+            input_type: find(&self.input_type),
+            input_amount: find(&self.input_amount),
+            output_type: find(&self.output_type),
+            output_amount: find(&self.output_amount),
+            account: self.account.as_ref().map(&mut find),
+            target_account: self.target_account.as_ref().map(&mut find),
+            fee_type: self.fee_type.as_ref().map(&mut find),
+            fee_amount: self.fee_amount.as_ref().map(&mut find),
+        };
+
+        if !invalid.is_empty() {
+            return Err(ColumnError::InvalidColumns { columns: invalid });
+        }
+
+        if missing.is_empty() {
+            Ok(indices)
+        } else {
+            Err(ColumnError::MissingHeaders {
+                missing,
+                found: headers.iter().map(|h| h.as_ref().to_string()).collect(),
+            })
+        }
+    }
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        ColumnMapping {
+            ordinal: None,
+            date: Self::default_date(),
+            tx_type: Self::default_tx_type(),
+            input_type: Self::default_input_type(),
+            input_amount: Self::default_input_amount(),
+            output_type: Self::default_output_type(),
+            output_amount: Self::default_output_amount(),
+            account: None,
+            target_account: None,
+            fee_type: None,
+            fee_amount: None,
+        }
+    }
+}
+
+/// Zero-based indices of the columns holding the transaction fields.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ColumnIndices {
+    pub ordinal: Option<usize>,
+    pub date: usize,
+    pub tx_type: usize,
+    pub input_type: usize,
+    pub input_amount: usize,
+    pub output_type: usize,
+    pub output_amount: usize,
+    pub account: Option<usize>,
+    pub target_account: Option<usize>,
+    pub fee_type: Option<usize>,
+    pub fee_amount: Option<usize>,
+}
+
+/// Convert the column letter(s) (e.g. `"A"` or `"AB"`) into a zero-based column index.
+/// Returns `None` if the column letters are invalid.
+pub fn column_index(column: &str) -> Option<usize> {
+    let column = column.trim();
+    if column.is_empty() || !column.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    let index = column
+        .to_ascii_uppercase()
+        .bytes()
+        .fold(0, |acc, c| acc * 26 + (c - b'A' + 1) as usize);
+
+    Some(index - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(toml: &str) -> ColumnMapping {
+        toml::from_str(toml).expect("Valid mapping in tests.")
+    }
+
+    #[test]
+    fn headers_are_matched_case_insensitively() {
+        let mapping = mapping(
+            r#"
+            ordinal = "No."
+            account = { column = "J" }
+            fee_type = "Fee Token"
+            fee_amount = "Fee"
+            "#,
+        );
+        assert!(mapping.uses_headers());
+
+        let headers = [
+            " fee ",
+            "No.",
+            "OUTPUT AMOUNT",
+            "Output Token",
+            "Input Amount",
+            "input token",
+            "Transaction Type",
+            "Date",
+            "Fee Token",
+        ];
+        let indices = mapping.resolve(&headers).unwrap();
+        assert_eq!(
+            indices,
+            ColumnIndices {
+                ordinal: Some(1),
+                date: 7,
+                tx_type: 6,
+                input_type: 5,
+                input_amount: 4,
+                output_type: 3,
+                output_amount: 2,
+                account: Some(9),
+                target_account: None,
+                fee_type: Some(8),
+                fee_amount: Some(0),
+            }
+        );
+    }
+
+    #[test]
+    fn missing_headers_are_all_reported() {
+        let headers = ["Date", "Transaction Type", "Input Token", "Output Token"];
+        let error = ColumnMapping::default().resolve(&headers).unwrap_err();
+
+        assert_eq!(
+            error,
+            ColumnError::MissingHeaders {
+                missing: vec!["Input Amount".to_string(), "Output Amount".to_string()],
+                found: headers.iter().map(|h| h.to_string()).collect(),
+            }
+        );
+    }
+
+    #[test]
+    fn positional_mapping_uses_column_letters() {
+        let mapping = ColumnMapping::positional(Some("H"), Some("I"), Some("j"), Some("AB"));
+        assert!(!mapping.uses_headers());
+
+        // Header row isn't needed for the column letters.
+        let indices = mapping.resolve::<&str>(&[]).unwrap();
+        assert_eq!(
+            indices,
+            ColumnIndices {
+                ordinal: Some(0),
+                date: 1,
+                tx_type: 2,
+                input_type: 3,
+                input_amount: 4,
+                output_type: 5,
+                output_amount: 6,
+                account: Some(7),
+                target_account: Some(8),
+                fee_type: Some(9),
+                fee_amount: Some(27),
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_columns_and_incomplete_fee_are_rejected() {
+        let error = ColumnMapping::positional(Some("H1"), None, None, None)
+            .resolve::<&str>(&[])
+            .unwrap_err();
+        assert_eq!(
+            error,
+            ColumnError::InvalidColumns {
+                columns: vec!["H1".to_string()]
+            }
+        );
+
+        let error = ColumnMapping::positional(None, None, Some("H"), None)
+            .resolve::<&str>(&[])
+            .unwrap_err();
+        assert_eq!(error, ColumnError::IncompleteFee);
+    }
+
+    #[test]
+    fn column_letters_to_index() {
+        assert_eq!(column_index("A"), Some(0));
+        assert_eq!(column_index("z"), Some(25));
+        assert_eq!(column_index(" AA "), Some(26));
+        assert_eq!(column_index("AZ"), Some(51));
+        assert_eq!(column_index(""), None);
+        assert_eq!(column_index("A1"), None);
+    }
+}
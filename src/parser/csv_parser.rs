@@ -0,0 +1,339 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::NaiveDate;
+use csv::StringRecord;
+use serde::Deserialize;
+use std::str::FromStr;
+
+use super::{
+    columns::{ColumnIndices, ColumnMapping},
+    text_format::TextFormat,
+};
+use crate::types::{
+    AssetType, CellError, ParseError, SourceLocation, Transaction, TransactionType,
+};
+
+/// Specification for the CSV file to parse.
+/// Defines path to the file, how the values are formatted, and which columns hold the transaction fields.
+#[derive(Debug, Deserialize)]
+pub struct CsvFileEntry {
+    /// Path to the CSV file.
+    file_path: String,
+    /// Delimiter used to separate the values, must be a single character.
+    #[serde(default = "CsvFileEntry::default_delimiter")]
+    delimiter: char,
+    /// Format of the dates & numbers.
+    #[serde(flatten)]
+    text_format: TextFormat,
+    /// Mapping of the transaction fields to the header names.
+    #[serde(default)]
+    columns: ColumnMapping,
+}
+
+impl CsvFileEntry {
+    fn default_delimiter() -> char {
+        ','
+    }
+
+    /// Path to the CSV file.
+    pub fn file_path(&self) -> &str {
+        &self.file_path
+    }
+}
+
+/// Parse the CSV file and return the transactions from it.
+pub(super) fn parse_csv_file(entry: &CsvFileEntry) -> Result<Vec<Transaction>, ParseError> {
+    let CsvFileEntry {
+        ref file_path,
+        delimiter,
+        ref text_format,
+        ref columns,
+    } = entry;
+
+    if !delimiter.is_ascii() {
+        return Err(ParseError::InvalidDelimiter {
+            file: file_path.to_string(),
+            delimiter: *delimiter,
+        });
+    }
+
+    let read_error = |source: csv::Error| ParseError::Read {
+        file: file_path.to_string(),
+        source: source.into(),
+    };
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(*delimiter as u8)
+        .trim(csv::Trim::All)
+        .from_path(file_path)
+        .map_err(read_error)?;
+
+    let file_name = file_path
+        .split('/')
+        .next_back()
+        .expect("File was opened hence it should have a name");
+    let file_location = SourceLocation::new(file_name);
+
+    let headers = reader.headers().map_err(read_error)?.clone();
+    let indices = columns
+        .resolve(&headers.iter().collect::<Vec<_>>())
+        .map_err(|source| ParseError::Columns {
+            location: file_location.clone(),
+            source,
+        })?;
+
+    let mut transactions = Vec::new();
+    let mut previous_date = NaiveDate::MIN;
+
+    for (idx, record) in reader.records().enumerate() {
+        let record = record.map_err(read_error)?;
+        // Header is the first line in the file.
+        let line_number = idx + 2;
+
+        // Skip the empty lines.
+        if record.iter().all(|value| value.is_empty()) {
+            continue;
+        }
+
+        let location = file_location.clone().with_row(line_number);
+
+        let transaction = parse_record(&record, &indices, text_format, idx as u32 + 1, &location)?;
+
+        // Ensure the dates are monotonically increasing.
+        if transaction.date() < previous_date {
+            return Err(ParseError::DateOrder {
+                location,
+                date: transaction.date(),
+                previous: previous_date,
+            });
+        }
+        previous_date = transaction.date();
+
+        transactions.push(transaction);
+    }
+
+    Ok(transactions)
+}
+
+/// Parse the CSV record into a `Transaction`.
+/// Each record is validated on its own, without any context of the previous records.
+///
+/// # Arguments
+/// * `record` - A single CSV record.
+/// * `indices` - Indices of the columns holding the transaction fields.
+/// * `text_format` - Format of the dates & numbers.
+/// * `default_ordinal` - Ordinal to use in case the ordinal column isn't mapped.
+/// * `location` - Location of the record, attached to the transaction & errors.
+fn parse_record(
+    record: &StringRecord,
+    indices: &ColumnIndices,
+    text_format: &TextFormat,
+    default_ordinal: u32,
+    location: &SourceLocation,
+) -> Result<Transaction, ParseError> {
+    let invalid = |field: &'static str| {
+        move |source: CellError| ParseError::InvalidCell {
+            location: location.clone(),
+            field,
+            source,
+        }
+    };
+
+    let field = |idx: usize, name: &'static str| {
+        record.get(idx).ok_or_else(|| {
+            invalid(name)(CellError::Missing {
+                column: format!("{}", idx + 1),
+            })
+        })
+    };
+
+    let ordinal = match indices.ordinal {
+        Some(idx) => {
+            let value = field(idx, "ordinal")?;
+            value.parse::<u32>().map_err(|_| {
+                invalid("ordinal")(CellError::InvalidOrdinal {
+                    value: value.to_string(),
+                })
+            })?
+        }
+        None => default_ordinal,
+    };
+
+    let (date, timestamp) = text_format
+        .parse_date_time(field(indices.date, "date")?)
+        .map_err(invalid("date"))?;
+
+    let tx_type = field(indices.tx_type, "transaction type")?;
+    let tx_type = TransactionType::from_str(tx_type).map_err(|_| {
+        invalid("transaction type")(CellError::InvalidTransactionType {
+            value: tx_type.to_string(),
+        })
+    })?;
+
+    let input_token = AssetType::from(field(indices.input_type, "input token")?.to_string());
+    let input_amount = text_format
+        .parse_decimal(field(indices.input_amount, "input amount")?)
+        .map_err(invalid("input amount"))?;
+
+    let output_token = AssetType::from(field(indices.output_type, "output token")?.to_string());
+    let output_amount = text_format
+        .parse_decimal(field(indices.output_amount, "output amount")?)
+        .map_err(invalid("output amount"))?;
+
+    let optional = |idx: Option<usize>| {
+        idx.and_then(|idx| record.get(idx))
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+
+    // Fee is present if the fee token isn't empty.
+    let fee = match (optional(indices.fee_type), indices.fee_amount) {
+        (Some(token), Some(idx)) => {
+            let amount = text_format
+                .parse_decimal(field(idx, "fee amount")?)
+                .map_err(invalid("fee amount"))?;
+            Some((AssetType::from(token), amount))
+        }
+        _ => None,
+    };
+
+    Ok(Transaction::new(
+        ordinal,
+        date,
+        tx_type,
+        input_token,
+        input_amount,
+        output_token,
+        output_amount,
+        location.clone(),
+    )
+    .new_with_timestamp(timestamp)
+    .new_with_accounts(optional(indices.account), optional(indices.target_account))
+    .new_with_fee(fee))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{asset, date, dec, temp_file};
+
+    fn entry(file_path: &str, options: &str) -> CsvFileEntry {
+        toml::from_str(&format!("file_path = {file_path:?}\n{options}"))
+            .expect("Valid entry in tests.")
+    }
+
+    fn location(file_path: &str, row: usize) -> SourceLocation {
+        let file_name = file_path.split('/').next_back().unwrap();
+        SourceLocation::new(file_name).with_row(row)
+    }
+
+    #[test]
+    fn columns_are_mapped_by_headers() {
+        let file_path = temp_file(
+            "headers.csv",
+            "Output Amount;Output Token;Input Amount;Input Token;Transaction Type;Date;Wallet\n\
+             0,5;BTC;1.000,25;EUR;Buying;01.02.2024;Kraken\n\
+             ;;;;;;\n\
+             1.500;EUR;0,5;BTC;Selling;03.02.2024;\n",
+        );
+        let entry = entry(
+            &file_path,
+            r#"
+            delimiter = ";"
+            date_format = "%d.%m.%Y"
+            decimal_separator = ","
+            thousands_separator = "."
+            columns = { account = "Wallet" }
+            "#,
+        );
+        let transactions = parse_csv_file(&entry).unwrap();
+        assert_eq!(transactions.len(), 2);
+
+        let buy = &transactions[0];
+        assert_eq!(buy.ordinal(), 1);
+        assert_eq!(buy.date(), date("2024-02-01"));
+        assert_eq!(buy.tx_type(), TransactionType::Buying);
+        assert_eq!(buy.input(), (asset("EUR"), dec("1000.25")));
+        assert_eq!(buy.output(), (asset("BTC"), dec("0.5")));
+        assert_eq!(buy.account(), Some("Kraken"));
+        assert_eq!(buy.location(), &location(&file_path, 2));
+
+        // Ordinal defaults to the record number, empty lines included.
+        let sale = &transactions[1];
+        assert_eq!(sale.ordinal(), 3);
+        assert_eq!(sale.output(), (asset("EUR"), dec("1500")));
+        assert_eq!(sale.account(), None);
+    }
+
+    #[test]
+    fn columns_are_mapped_by_letters() {
+        let file_path = temp_file(
+            "letters.csv",
+            "a,b,c,d,e,f,g,h,i\n\
+             7,2024-01-01,Buying,EUR,100,BTC,1,EUR,1.5\n",
+        );
+        let entry = entry(
+            &file_path,
+            r#"
+            [columns]
+            ordinal = { column = "A" }
+            date = { column = "B" }
+            tx_type = { column = "C" }
+            input_type = { column = "D" }
+            input_amount = { column = "E" }
+            output_type = { column = "F" }
+            output_amount = { column = "G" }
+            fee_type = { column = "H" }
+            fee_amount = { column = "I" }
+            "#,
+        );
+        let transactions = parse_csv_file(&entry).unwrap();
+
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].ordinal(), 7);
+        assert_eq!(transactions[0].fee(), Some(&(asset("EUR"), dec("1.5"))));
+    }
+
+    #[test]
+    fn invalid_values_name_the_field_and_location() {
+        let header = "Date,Transaction Type,Input Token,Input Amount,Output Token,Output Amount\n";
+        let file_path = temp_file(
+            "invalid.csv",
+            &format!("{header}2024-01-01,Buying,EUR,1O0,BTC,1\n"),
+        );
+        let error = parse_csv_file(&entry(&file_path, "")).unwrap_err();
+        match error {
+            ParseError::InvalidCell {
+                location: error_location,
+                field,
+                source: CellError::InvalidNumber { value, .. },
+            } => {
+                assert_eq!(error_location, location(&file_path, 2));
+                assert_eq!(field, "input amount");
+                assert_eq!(value, "1O0");
+            }
+            error => panic!("Unexpected error: {error}"),
+        }
+
+        let file_path = temp_file(
+            "order.csv",
+            &format!("{header}2024-01-02,Buying,EUR,100,BTC,1\n2024-01-01,Buying,EUR,100,BTC,1\n"),
+        );
+        let error = parse_csv_file(&entry(&file_path, "")).unwrap_err();
+        assert!(matches!(
+            error,
+            ParseError::DateOrder { date: found, previous, .. }
+                if found == date("2024-01-01") && previous == date("2024-01-02")
+        ));
+    }
+}
@@ -10,4 +10,100 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod columns;
+pub mod csv_parser;
+mod text_format;
 pub mod xlsx_parser;
+mod xlsx_raw;
+
+use csv_parser::CsvFileEntry;
+use xlsx_parser::XlsxFileEntry;
+
+use crate::types::ParserDataType;
+
+use serde::{de::Error, Deserialize, Deserializer};
+
+/// Specification of a single data source to parse.
+///
+/// Format of the source is selected with the `format` field, `xlsx` being the default.
+#[derive(Debug)]
+pub enum SourceEntry {
+    /// Sheet in an XLSX file.
+    Xlsx(XlsxFileEntry),
+    /// CSV file.
+    Csv(CsvFileEntry),
+}
+
+impl SourceEntry {
+    /// Parse the source and return the transactions from it.
+    pub fn parse(&self) -> ParserDataType {
+        match self {
+            SourceEntry::Xlsx(entry) => xlsx_parser::parse_xlsx_file(entry),
+            SourceEntry::Csv(entry) => csv_parser::parse_csv_file(entry),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SourceEntry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let table = toml::Table::deserialize(deserializer)?;
+
+        let format = match table.get("format") {
+            Some(toml::Value::String(format)) => format.to_lowercase(),
+            Some(value) => {
+                return Err(D::Error::custom(format!(
+                    "Entry format must be a string, found: {value}"
+                )))
+            }
+            None => "xlsx".to_string(),
+        };
+
+        match format.as_str() {
+            "xlsx" => table.try_into().map(SourceEntry::Xlsx),
+            "csv" => table.try_into().map(SourceEntry::Csv),
+            _ => {
+                return Err(D::Error::custom(format!(
+                    "Unsupported entry format '{format}', expected 'xlsx' or 'csv'"
+                )))
+            }
+        }
+        .map_err(D::Error::custom)
+    }
+}
+
+/// Implementation of the transaction provider for parsing the configured data sources.
+///
+/// Sources are parsed in the order they were provided.
+pub struct SourceParser {
+    entries: Vec<SourceEntry>,
+    index: usize,
+}
+
+impl SourceParser {
+    pub fn new(entries: Vec<SourceEntry>) -> Self {
+        Self { entries, index: 0 }
+    }
+}
+
+impl Iterator for SourceParser {
+    type Item = ParserDataType;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.entries.get(self.index)?;
+        let result = entry.parse();
+        self.index += 1;
+
+        match entry {
+            SourceEntry::Xlsx(entry) => log::debug!(
+                "Parsed transactions from file: {}, sheet: {}",
+                entry.file_path(),
+                entry.sheet_name()
+            ),
+            SourceEntry::Csv(entry) => {
+                log::debug!("Parsed transactions from file: {}", entry.file_path())
+            }
+        }
+
+        Some(result)
+    }
+}
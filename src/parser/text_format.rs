@@ -0,0 +1,255 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Deserializer};
+use std::str::FromStr;
+
+use crate::types::CellError;
+
+/// Format of the dates & numbers stored as text in the input data.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TextFormat {
+    /// Accepted formats of the dates, using `chrono` syntax (e.g. `%d.%m.%Y`).
+    /// Formats are tried in order, and the first one that matches is used.
+    /// Formats with time (e.g. `%Y-%m-%d %H:%M:%S`) and UTC offset (e.g. `%Y-%m-%dT%H:%M:%S%z`) are supported as well.
+    #[serde(
+        default = "TextFormat::default_date_formats",
+        alias = "date_format",
+        deserialize_with = "one_or_many"
+    )]
+    date_formats: Vec<String>,
+    /// Character used as a decimal separator (e.g. `.` or `,`).
+    #[serde(default = "TextFormat::default_decimal_separator")]
+    decimal_separator: char,
+    /// Character used to group the thousands (e.g. `,` or `.`), if any.
+    #[serde(default)]
+    thousands_separator: Option<char>,
+    /// Timezone of the times without a UTC offset (e.g. `Europe/Berlin`), UTC by default.
+    #[serde(default = "TextFormat::default_timezone")]
+    timezone: Tz,
+}
+
+impl TextFormat {
+    fn default_date_formats() -> Vec<String> {
+        [
+            "%Y-%m-%d",
+            "%Y-%m-%d %H:%M:%S",
+            "%Y-%m-%dT%H:%M:%S",
+            "%d.%m.%Y",
+            "%d.%m.%Y %H:%M:%S",
+            "%d-%b-%Y",
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .collect()
+    }
+
+    fn default_decimal_separator() -> char {
+        '.'
+    }
+
+    fn default_timezone() -> Tz {
+        Tz::UTC
+    }
+
+    /// Parse the date, and the exact time if present, from the text.
+    ///
+    /// # Returns
+    /// * `NaiveDate` - Date as written in the text.
+    /// * `Option<DateTime<Utc>>` - Time of the transaction, if the text includes it.
+    pub fn parse_date_time(
+        &self,
+        value: &str,
+    ) -> Result<(NaiveDate, Option<DateTime<Utc>>), CellError> {
+        let value = value.trim();
+
+        // Formats with time would also match as a plain date, hence the most specific one is tried first.
+        let parsed = self.date_formats.iter().find_map(|format| {
+            if let Ok(date_time) = DateTime::parse_from_str(value, format) {
+                Some(Ok((date_time.date_naive(), Some(date_time.to_utc()))))
+            } else if let Ok(date_time) = NaiveDateTime::parse_from_str(value, format) {
+                Some(
+                    self.to_utc(date_time)
+                        .map(|timestamp| (date_time.date(), Some(timestamp))),
+                )
+            } else {
+                NaiveDate::parse_from_str(value, format)
+                    .ok()
+                    .map(|date| Ok((date, None)))
+            }
+        });
+
+        parsed.unwrap_or_else(|| {
+            Err(CellError::InvalidDate {
+                value: value.to_string(),
+                formats: self.date_formats.clone(),
+            })
+        })
+    }
+
+    /// Convert the local time, expressed in the configured timezone, into UTC.
+    ///
+    /// Ambiguous times (e.g. when the clocks go back) resolve to the earlier instant.
+    pub fn to_utc(&self, date_time: NaiveDateTime) -> Result<DateTime<Utc>, CellError> {
+        self.timezone
+            .from_local_datetime(&date_time)
+            .earliest()
+            .map(|date_time| date_time.to_utc())
+            .ok_or_else(|| CellError::NonexistentTime {
+                value: date_time.to_string(),
+                timezone: self.timezone.to_string(),
+            })
+    }
+
+    /// Parse the decimal number from the text.
+    /// The text is parsed exactly, without any floating point conversion.
+    pub fn parse_decimal(&self, value: &str) -> Result<Decimal, CellError> {
+        if Some(self.decimal_separator) == self.thousands_separator {
+            return Err(CellError::ConflictingSeparators {
+                separator: self.decimal_separator,
+            });
+        }
+
+        let value = value.trim();
+        let normalized: String = value
+            .chars()
+            .filter(|c| Some(*c) != self.thousands_separator)
+            .map(|c| if c == self.decimal_separator { '.' } else { c })
+            .collect();
+
+        Decimal::from_str(&normalized)
+            .or_else(|_| Decimal::from_scientific(&normalized))
+            .map_err(|e| CellError::InvalidNumber {
+                value: value.to_string(),
+                reason: e.to_string(),
+            })
+    }
+}
+
+impl Default for TextFormat {
+    fn default() -> Self {
+        TextFormat {
+            date_formats: Self::default_date_formats(),
+            decimal_separator: Self::default_decimal_separator(),
+            thousands_separator: None,
+            timezone: Self::default_timezone(),
+        }
+    }
+}
+
+/// Deserialize either a single string, or a list of strings.
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{date, dec};
+
+    fn text_format(toml: &str) -> TextFormat {
+        toml::from_str(toml).expect("Valid format in tests.")
+    }
+
+    fn utc(value: &str) -> Option<DateTime<Utc>> {
+        Some(DateTime::parse_from_rfc3339(value).unwrap().to_utc())
+    }
+
+    #[test]
+    fn default_formats_parse_dates_with_and_without_time() {
+        let format = TextFormat::default();
+
+        assert_eq!(
+            format.parse_date_time(" 2024-03-01 ").unwrap(),
+            (date("2024-03-01"), None)
+        );
+        assert_eq!(
+            format.parse_date_time("01.03.2024").unwrap(),
+            (date("2024-03-01"), None)
+        );
+        assert_eq!(
+            format.parse_date_time("1-Mar-2024").unwrap(),
+            (date("2024-03-01"), None)
+        );
+        assert_eq!(
+            format.parse_date_time("2024-03-01T23:15:00").unwrap(),
+            (date("2024-03-01"), utc("2024-03-01T23:15:00Z"))
+        );
+
+        let error = format.parse_date_time("2024/03/01").unwrap_err();
+        assert!(matches!(error, CellError::InvalidDate { value, .. } if value == "2024/03/01"));
+    }
+
+    #[test]
+    fn times_are_converted_from_the_source_timezone() {
+        let format = text_format(
+            r#"
+            date_formats = ["%Y-%m-%dT%H:%M:%S%z", "%d/%m/%Y %H:%M"]
+            timezone = "America/New_York"
+            "#,
+        );
+
+        // Date is kept as written, the timestamp is in UTC.
+        assert_eq!(
+            format.parse_date_time("01/03/2024 21:30").unwrap(),
+            (date("2024-03-01"), utc("2024-03-02T02:30:00Z"))
+        );
+        // Explicit UTC offset takes precedence over the timezone.
+        assert_eq!(
+            format.parse_date_time("2024-03-01T21:30:00+0100").unwrap(),
+            (date("2024-03-01"), utc("2024-03-01T20:30:00Z"))
+        );
+        // Clocks go forward at 2:00 on the 10th of March.
+        let error = format.parse_date_time("10/03/2024 02:30").unwrap_err();
+        assert!(matches!(error, CellError::NonexistentTime { .. }));
+    }
+
+    #[test]
+    fn decimals_with_custom_separators() {
+        let format = text_format(
+            r#"
+            decimal_separator = ","
+            thousands_separator = " "
+            "#,
+        );
+
+        assert_eq!(
+            format.parse_decimal("1 234 567,891").unwrap(),
+            dec("1234567.891")
+        );
+        assert_eq!(format.parse_decimal("-0,5").unwrap(), dec("-0.5"));
+        assert_eq!(format.parse_decimal("1,5e-3").unwrap(), dec("0.0015"));
+        assert!(matches!(
+            format.parse_decimal("1,2,3"),
+            Err(CellError::InvalidNumber { .. })
+        ));
+
+        let format = text_format(r#"thousands_separator = ".""#);
+        assert_eq!(
+            format.parse_decimal("1.5"),
+            Err(CellError::ConflictingSeparators { separator: '.' })
+        );
+    }
+}
@@ -10,16 +10,26 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use calamine::{open_workbook, Data, DataType, Reader, Xlsx};
-use chrono::NaiveDate;
+use calamine::{open_workbook, Data, DataType, Reader, Xlsx, XlsxError};
+use chrono::{NaiveDate, NaiveTime};
 use rust_decimal::Decimal;
 use serde::Deserialize;
 use std::str::FromStr;
 
-use crate::types::{AssetType, ParserDataType, Transaction, TransactionType};
+use super::{
+    columns::{ColumnIndices, ColumnMapping},
+    text_format::TextFormat,
+    xlsx_raw::RawSheetValues,
+};
+use crate::types::{
+    AssetType, CellError, ParseError, SourceLocation, Transaction, TransactionType,
+};
 
 /// Specification for the XLSX file to parse.
 /// Defines path to the file, which sheet to read from, and from which row to start reading.
+///
+/// By default, the columns are expected in the fixed order (see README).
+/// Alternatively, `columns` can map the transaction fields to the header names or column letters.
 #[derive(Debug, Deserialize)]
 pub struct XlsxFileEntry {
     /// Path to the XLSX file.
@@ -28,130 +38,182 @@ pub struct XlsxFileEntry {
     sheet_name: String,
     /// Row number from which to start reading the data.
     start_row: usize,
+    /// Column (e.g. `"H"`) holding the account (wallet or exchange) of the transaction, if any.
+    account_column: Option<String>,
+    /// Column holding the account receiving the assets of a transfer, if any.
+    target_account_column: Option<String>,
+    /// Column holding the fee token of the transaction, if any.
+    fee_type_column: Option<String>,
+    /// Column holding the fee amount of the transaction, if any.
+    fee_amount_column: Option<String>,
+    /// Row number of the header row. Uses `Excel-like` indexing (1-based).
+    /// Defaults to the `start_row`, i.e. the row right before the first transaction.
+    header_row: Option<usize>,
+    /// Mapping of the transaction fields to the header names or column letters.
+    /// If not specified, the fixed column order is used.
+    columns: Option<ColumnMapping>,
+    /// Format of the dates & numbers stored as text cells.
+    #[serde(flatten)]
+    text_format: TextFormat,
 }
 
-/// Implementation of the transaction provider for parsing XLSX files.
-pub struct XlsxParser {
-    entries: Vec<XlsxFileEntry>,
-    index: usize,
-}
-
-impl XlsxParser {
-    pub fn new(entries: Vec<XlsxFileEntry>) -> Self {
-        Self { entries, index: 0 }
-    }
-
-    /// Parse the XLSX file and return the transactions from the specified sheet.
-    fn parse_xlsx_file(
-        entry: &XlsxFileEntry,
-    ) -> Result<Vec<Transaction>, Box<dyn std::error::Error>> {
-        let XlsxFileEntry {
-            ref file_path,
-            ref sheet_name,
-            start_row,
-        } = entry;
-
-        let mut workbook: Xlsx<_> = open_workbook(file_path)?;
-
-        if let Ok(range) = workbook.worksheet_range(sheet_name) {
-            let mut row_number = *start_row;
-            let mut previous_date = NaiveDate::MIN;
-
-            let file_name = file_path
-                .split('/')
-                .next_back()
-                .expect("File was opened hence it should have a name");
-
-            // If possible, check if the first row above start row has an ordinal number.
-            // If it does, print a warning to the user that their config might be skipping data.
-            if row_number > 0 {
-                if let Some(row) = range.rows().nth(start_row.saturating_sub(1)) {
-                    if let Some(Data::Float(_)) = row.first() {
-                        log::warn!(
-                            "The row before the specified start row ({}) in file: '{}', sheet: '{}' has an ordinal number. \
-                            Please check if your config is correct and not skipping any data.",
-                            start_row,
-                            file_name,
-                            sheet_name
-                        );
-                    }
-                }
-            }
-
-            let mut transactions = Vec::new();
+impl XlsxFileEntry {
+    /// Path to the XLSX file.
+    pub fn file_path(&self) -> &str {
+        &self.file_path
+    }
 
-            // 1. Iterate over the rows, and validate data.
-            for row in range.rows().skip(*start_row) {
-                // Stop reading when the first date cell is empty.
-                if let Some(Data::Empty) = row.get(1) {
-                    break;
-                }
+    /// Name of the sheet to read from.
+    pub fn sheet_name(&self) -> &str {
+        &self.sheet_name
+    }
+}
 
-                let context_message = format!(
-                    "File: '{}', Sheet: '{}, Row: {}'",
+/// Parse the XLSX file and return the transactions from the specified sheet.
+pub(super) fn parse_xlsx_file(entry: &XlsxFileEntry) -> Result<Vec<Transaction>, ParseError> {
+    let XlsxFileEntry {
+        ref file_path,
+        ref sheet_name,
+        start_row,
+        ref account_column,
+        ref target_account_column,
+        ref fee_type_column,
+        ref fee_amount_column,
+        header_row,
+        ref columns,
+        ref text_format,
+    } = entry;
+
+    let mut workbook: Xlsx<_> =
+        open_workbook(file_path).map_err(|e: XlsxError| ParseError::Read {
+            file: file_path.to_string(),
+            source: e.into(),
+        })?;
+
+    let file_name = file_path
+        .split('/')
+        .next_back()
+        .expect("File was opened hence it should have a name");
+
+    let range = workbook
+        .worksheet_range(sheet_name)
+        .map_err(|_| ParseError::SheetNotFound {
+            file: file_name.to_string(),
+            sheet: sheet_name.to_string(),
+        })?;
+
+    let sheet_location = SourceLocation::new(file_name).with_sheet(sheet_name);
+    let mut row_number = *start_row;
+    let mut previous_date = NaiveDate::MIN;
+
+    // Raw text of the numeric cells, used to read the amounts without `f64` rounding.
+    let raw_values = RawSheetValues::load(file_path, sheet_name).unwrap_or_else(|e| {
+        log::warn!(
+            "{sheet_location}; Cannot read raw cell values, amounts might be rounded. Error: {e}"
+        );
+        RawSheetValues::default()
+    });
+    let (start_row_offset, start_col_offset) = range.start().unwrap_or_default();
+
+    // Resolve the columns holding the transaction fields.
+    let columns = match columns {
+        Some(columns) => columns.clone(),
+        None => ColumnMapping::positional(
+            account_column.as_deref(),
+            target_account_column.as_deref(),
+            fee_type_column.as_deref(),
+            fee_amount_column.as_deref(),
+        ),
+    };
+    let headers = if columns.uses_headers() {
+        let header_row = header_row.unwrap_or(*start_row);
+        let row = header_row
+            .checked_sub(1)
+            .and_then(|idx| range.rows().nth(idx))
+            .ok_or_else(|| ParseError::MissingHeaderRow {
+                location: sheet_location.clone().with_row(header_row),
+            })?;
+        row.iter().map(|cell| cell.to_string()).collect()
+    } else {
+        Vec::new()
+    };
+    let indices = columns
+        .resolve(&headers)
+        .map_err(|source| ParseError::Columns {
+            location: sheet_location.clone(),
+            source,
+        })?;
+
+    // If possible, check if the first row above start row has an ordinal number.
+    // If it does, print a warning to the user that their config might be skipping data.
+    if let (true, Some(ordinal_idx)) = (row_number > 0, indices.ordinal) {
+        if let Some(row) = range.rows().nth(start_row.saturating_sub(1)) {
+            if let Some(Data::Float(_)) = row.get(ordinal_idx) {
+                log::warn!(
+                    "The row before the specified start row ({}) in file: '{}', sheet: '{}' has an ordinal number. \
+                    Please check if your config is correct and not skipping any data.",
+                    start_row,
                     file_name,
-                    sheet_name,
-                    row_number + 1
+                    sheet_name
                 );
-
-                transactions.push(parse_row(row, &context_message).map_err(|message| {
-                    format!(
-                        "{context_message}; row number: {row_number}, has invalid data - please check! Error: {message}",
-                    )
-                })?);
-
-                // Ensure the dates are monotonically increasing.
-                if let Some(tx) = transactions.last() {
-                    if tx.date() < previous_date {
-                        return Err(format!(
-                            "{context_message}; row number: {row_number}, has a date that is not monotonically increasing - please check!",
-                        ).into());
-                    }
-                    previous_date = tx.date();
-                }
-
-                row_number += 1;
             }
+        }
+    }
 
-            // 2. Ensure this & and a few following cells are actually empty.
-            // This is to ensure we don't accidentally skip some data.
-            for row in range.rows().skip(row_number).take(3) {
-                if row.get(1) != Some(&Data::Empty) {
-                    return Err(format!(
-                        "Row {row:?}, number {row_number} in sheet {sheet_name}, has non-empty cells after the first empty cell - please check!",
-                    ).into());
-                }
-
-                row_number += 1;
-            }
+    let mut transactions = Vec::new();
 
-            Ok(transactions)
-        } else {
-            Err(format!("Sheet '{sheet_name}' not found").into())
+    // 1. Iterate over the rows, and validate data.
+    for row in range.rows().skip(*start_row) {
+        // Stop reading when the first date cell is empty.
+        if matches!(row.get(indices.date), Some(Data::Empty) | None) {
+            break;
         }
-    }
-}
 
-impl Iterator for XlsxParser {
-    type Item = ParserDataType;
+        let location = sheet_location.clone().with_row(row_number + 1);
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.index < self.entries.len() {
-            let entry = &self.entries[self.index];
-            let result = Self::parse_xlsx_file(entry);
-            self.index += 1;
+        let default_ordinal = transactions.len() as u32 + 1;
+        let raw_value = |col: usize| {
+            raw_values.get(
+                start_row_offset as usize + row_number,
+                start_col_offset as usize + col,
+            )
+        };
+        let transaction = parse_row(
+            row,
+            &indices,
+            text_format,
+            raw_value,
+            default_ordinal,
+            &location,
+        )?;
+
+        // Ensure the dates are monotonically increasing.
+        if transaction.date() < previous_date {
+            return Err(ParseError::DateOrder {
+                location,
+                date: transaction.date(),
+                previous: previous_date,
+            });
+        }
+        previous_date = transaction.date();
+        transactions.push(transaction);
 
-            log::debug!(
-                "Parsed transactions from file: {}, sheet: {}",
-                entry.file_path,
-                entry.sheet_name
-            );
+        row_number += 1;
+    }
 
-            Some(result)
-        } else {
-            None
+    // 2. Ensure this & and a few following cells are actually empty.
+    // This is to ensure we don't accidentally skip some data.
+    for row in range.rows().skip(row_number).take(3) {
+        if !matches!(row.get(indices.date), Some(Data::Empty) | None) {
+            return Err(ParseError::DataAfterEnd {
+                location: sheet_location.with_row(row_number + 1),
+            });
         }
+
+        row_number += 1;
     }
+
+    Ok(transactions)
 }
 
 /// Validate the row data against the expected format, and return the `Transaction`.
@@ -159,88 +221,133 @@ impl Iterator for XlsxParser {
 ///
 /// # Arguments
 /// * `row` - A row of data. Should be in the appropriate format.
-/// * `extra_info` - Extra info to attach to the transaction (e.g. filename, sheet, row).
+/// * `indices` - Indices of the columns holding the transaction fields.
+/// * `text_format` - Format of the dates & numbers stored as text.
+/// * `raw_value` - Raw text of the numeric cell in the specified column, if available.
+/// * `default_ordinal` - Ordinal to use in case the ordinal column isn't mapped.
+/// * `location` - Location of the row, attached to the transaction & errors.
 ///
 /// # Returns
 /// * `Transaction` - If the row is valid, return the parsed transaction.
-/// * `String` - If the row is invalid, return an error message.
-fn parse_row(row: &[Data], extra_info: &str) -> Result<Transaction, String> {
-    if row.len() < 8 {
-        return Err(format!(
-            "Row is too short, expecting at least 8 columns, skipping: {row:?}"
-        ));
-    }
+/// * `ParseError` - If the row is invalid, return the error describing the invalid cell.
+fn parse_row<'a>(
+    row: &[Data],
+    indices: &ColumnIndices,
+    text_format: &TextFormat,
+    raw_value: impl Fn(usize) -> Option<&'a str>,
+    default_ordinal: u32,
+    location: &SourceLocation,
+) -> Result<Transaction, ParseError> {
+    let invalid = |field: &'static str| {
+        move |source: CellError| ParseError::InvalidCell {
+            location: location.clone(),
+            field,
+            source,
+        }
+    };
+    let unexpected = |field: &'static str, expected: &'static str, data: &Data| {
+        invalid(field)(CellError::UnexpectedType {
+            expected,
+            found: format!("{data:?}"),
+        })
+    };
 
-    // Helper function to parse a float as a decimal
-    fn parse_decimal(data: &Data, field: &str) -> Result<Decimal, String> {
-        if let Data::Float(_) = data {
-            Decimal::from_str(
-                &data
-                    .as_string()
-                    .expect("Float can be represented as string."),
-            )
-            .map_err(|e| format!("Cannot parse {field}, failed converting float to Decimal: {e:?}"))
-        } else {
-            Err(format!(
-                "Expected a decimal value for {field}, found: {data:?}"
-            ))
+    // Helper function to get the cell, or report the missing one.
+    let cell = |idx: usize, field: &'static str| {
+        row.get(idx).ok_or_else(|| {
+            invalid(field)(CellError::Missing {
+                column: column_name(idx),
+            })
+        })
+    };
+
+    // Helper function to parse a number or text cell as a decimal
+    let parse_decimal = |idx: usize, field: &'static str| -> Result<Decimal, ParseError> {
+        let data = cell(idx, field)?;
+        match data {
+            Data::Float(_) => {
+                numeric_to_decimal(data, raw_value(idx), field, location).map_err(invalid(field))
+            }
+            Data::Int(value) => Ok(Decimal::from(*value)),
+            Data::String(value) => text_format.parse_decimal(value).map_err(invalid(field)),
+            _ => Err(unexpected(field, "a decimal value", data)),
         }
-    }
+    };
 
     // Helper function to parse a string
-    fn parse_string<'a>(data: &'a Data, field: &'a str) -> Result<&'a str, String> {
-        if let Data::String(value) = data {
-            Ok(value)
-        } else {
-            Err(format!("Expected a string for {field}, found: {data:?}"))
+    let parse_string = |idx: usize, field: &'static str| -> Result<&str, ParseError> {
+        match cell(idx, field)? {
+            Data::String(value) => Ok(value),
+            data => Err(unexpected(field, "a string", data)),
         }
-    }
+    };
 
     // 1. Parse the ordinal value.
-    let ordinal = match row[0] {
-        Data::Float(value) if value.fract() == 0.0 => value as u32,
-        _ => {
-            return Err(format!(
-                "First column must be an ordinal (integer), skipping: {row:?}",
-            ))
-        }
+    let ordinal = match indices.ordinal {
+        Some(idx) => match cell(idx, "ordinal")? {
+            Data::Float(value) if value.fract() == 0.0 => *value as u32,
+            Data::Int(value) if *value >= 0 => *value as u32,
+            data => {
+                return Err(invalid("ordinal")(CellError::InvalidOrdinal {
+                    value: data.to_string(),
+                }))
+            }
+        },
+        None => default_ordinal,
     };
 
-    // 2. Parse the date.
-    let date = match row[1] {
-        Data::DateTime(date) => date,
-        _ => return Err(format!("Second column must be a date, skipping: {row:?}")),
+    // 2. Parse the date, and the time if present.
+    // Date cells without a time part (i.e. at midnight) are treated as plain dates.
+    let (date, timestamp) = match cell(indices.date, "date")? {
+        Data::DateTime(date) => {
+            let date_time = date
+                .as_datetime()
+                .ok_or_else(|| unexpected("date", "a valid date", &Data::DateTime(*date)))?;
+            let timestamp = if date_time.time() == NaiveTime::MIN {
+                None
+            } else {
+                Some(text_format.to_utc(date_time).map_err(invalid("date"))?)
+            };
+            (date_time.date(), timestamp)
+        }
+        Data::String(value) | Data::DateTimeIso(value) => text_format
+            .parse_date_time(value)
+            .map_err(invalid("date"))?,
+        data => return Err(unexpected("date", "a date", data)),
     };
-    let date = date
-        .as_datetime()
-        .ok_or_else(|| {
-            format!("Cannot convert second column date to `Datetime`, skipping: {row:?}",)
-        })?
-        .date();
 
     // 3. Parse the action type.
-    let action_type = if let Data::String(value) = &row[2] {
-        TransactionType::from_str(value)
-            .map_err(|_| format!("Third column must be a valid action type, skipping: {row:?}",))?
-    } else {
-        return Err(format!(
-            "Third column must be a string (action type), skipping: {row:?}",
-        ));
-    };
+    let action_type = parse_string(indices.tx_type, "transaction type")?;
+    let action_type = TransactionType::from_str(action_type).map_err(|_| {
+        invalid("transaction type")(CellError::InvalidTransactionType {
+            value: action_type.to_string(),
+        })
+    })?;
 
     // 4. Parse the input token.
-    let input_token = AssetType::from_str(parse_string(&row[3], "input token")?)
-        .map_err(|_| format!("Fourth column must be a valid asset type, skipping: {row:?}",))?;
+    let input_token = AssetType::from(parse_string(indices.input_type, "input token")?.to_string());
 
     // 5. Parse the input amount.
-    let input_amount = parse_decimal(&row[4], "input amount")?;
+    let input_amount = parse_decimal(indices.input_amount, "input amount")?;
 
     // 6. Parse the output token.
-    let output_token = AssetType::from_str(parse_string(&row[5], "output token")?)
-        .map_err(|_| format!("Fifth column must be a valid asset type, skipping: {row:?}"))?;
+    let output_token =
+        AssetType::from(parse_string(indices.output_type, "output token")?.to_string());
 
     // 7. Parse the output amount.
-    let output_amount = parse_decimal(&row[6], "output amount")?;
+    let output_amount = parse_decimal(indices.output_amount, "output amount")?;
+
+    // 8. Parse the optional accounts, empty cells are treated as an unknown account.
+    let account = parse_text(row, indices.account);
+    let target_account = parse_text(row, indices.target_account);
+
+    // 9. Parse the optional fee, present if the fee token cell isn't empty.
+    let fee = match (parse_text(row, indices.fee_type), indices.fee_amount) {
+        (Some(token), Some(idx)) => {
+            Some((AssetType::from(token), parse_decimal(idx, "fee amount")?))
+        }
+        _ => None,
+    };
 
     Ok(Transaction::new(
         ordinal,
@@ -250,6 +357,369 @@ fn parse_row(row: &[Data], extra_info: &str) -> Result<Transaction, String> {
         input_amount,
         output_token,
         output_amount,
-        extra_info.to_string(),
-    ))
+        location.clone(),
+    )
+    .new_with_timestamp(timestamp)
+    .new_with_accounts(account, target_account)
+    .new_with_fee(fee))
+}
+
+/// Maximum number of significant digits which Excel keeps reliably.
+const MAX_RELIABLE_DIGITS: usize = 15;
+
+/// Convert the numeric cell into a `Decimal`.
+///
+/// The raw text of the cell is preferred, since it isn't affected by the `f64` rounding.
+/// In case the value might have been rounded, a warning with the row context is logged.
+fn numeric_to_decimal(
+    data: &Data,
+    raw: Option<&str>,
+    field: &str,
+    location: &SourceLocation,
+) -> Result<Decimal, CellError> {
+    if let Some(raw) = raw {
+        match Decimal::from_str(raw).or_else(|_| Decimal::from_scientific(raw)) {
+            Ok(value) => {
+                if significant_digits(raw) > MAX_RELIABLE_DIGITS {
+                    log::warn!(
+                        "{location}; Value '{raw}' of {field} has more than {MAX_RELIABLE_DIGITS} significant digits, \
+                        Excel might have rounded it. Consider storing the amount as text."
+                    );
+                }
+                return Ok(value);
+            }
+            Err(e) => log::warn!(
+                "{location}; Cannot parse raw value '{raw}' of {field} ({e}), using the floating point value instead."
+            ),
+        }
+    }
+
+    let float_value = data
+        .as_string()
+        .expect("Float can be represented as string.");
+    let value = Decimal::from_str(&float_value)
+        .or_else(|_| Decimal::from_scientific(&float_value))
+        .map_err(|e| CellError::InvalidNumber {
+            value: float_value.clone(),
+            reason: e.to_string(),
+        })?;
+
+    if significant_digits(&float_value) > MAX_RELIABLE_DIGITS {
+        log::warn!(
+            "{location}; Floating point value '{float_value}' of {field} might be rounded, since its raw value isn't available."
+        );
+    }
+
+    Ok(value)
+}
+
+/// Number of significant digits in the textual number representation.
+fn significant_digits(value: &str) -> usize {
+    let mantissa = value
+        .split(['e', 'E'])
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches(['-', '+']);
+    let digits: String = mantissa.chars().filter(char::is_ascii_digit).collect();
+
+    digits.trim_start_matches('0').trim_end_matches('0').len()
+}
+
+/// Convert the zero-based column index into the column letter(s), e.g. `0` into `A`.
+fn column_name(idx: usize) -> String {
+    let mut name = String::new();
+    let mut idx = idx + 1;
+    while idx > 0 {
+        let remainder = (idx - 1) % 26;
+        name.insert(0, (b'A' + remainder as u8) as char);
+        idx = (idx - 1) / 26;
+    }
+    name
+}
+
+/// Parse the trimmed text from the specified column, if any.
+/// Empty cells are treated as a missing value.
+fn parse_text(row: &[Data], column: Option<usize>) -> Option<String> {
+    let value = row.get(column?)?.as_string()?;
+    let value = value.trim();
+
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{asset, date, dec, temp_path};
+
+    use rust_xlsxwriter::{ExcelDateTime, Format, Workbook, XlsxError as WriteError};
+    use std::{
+        fs::File,
+        io::{Read, Write},
+    };
+    use zip::{write::SimpleFileOptions, ZipArchive, ZipWriter};
+
+    /// Value written into a cell of the test workbook.
+    enum Value {
+        Text(&'static str),
+        Number(f64),
+        Date(u16, u8, u8),
+        Blank,
+    }
+    use Value::{Blank, Date, Number, Text};
+
+    /// Write the rows into the `Transactions` sheet of a new workbook, and return its path.
+    fn workbook(name: &str, rows: &[Vec<Value>]) -> Result<String, WriteError> {
+        let mut workbook = Workbook::new();
+        let sheet = workbook.add_worksheet().set_name("Transactions")?;
+        let date_format = Format::new().set_num_format("yyyy-mm-dd");
+
+        for (row_idx, row) in rows.iter().enumerate() {
+            for (col_idx, value) in row.iter().enumerate() {
+                let (row_idx, col_idx) = (row_idx as u32, col_idx as u16);
+                match value {
+                    Text(text) => sheet.write_string(row_idx, col_idx, *text)?,
+                    Number(number) => sheet.write_number(row_idx, col_idx, *number)?,
+                    Date(year, month, day) => sheet.write_datetime_with_format(
+                        row_idx,
+                        col_idx,
+                        ExcelDateTime::from_ymd(*year, *month, *day)?,
+                        &date_format,
+                    )?,
+                    Blank => continue,
+                };
+            }
+        }
+
+        let path = temp_path(name);
+        workbook.save(&path)?;
+        Ok(path.to_string_lossy().into_owned())
+    }
+
+    /// Replace the text in the sheet XML, e.g. to store more digits than `f64` can hold.
+    fn patch_sheet(file_path: &str, from: &str, to: &str) {
+        let mut archive = ZipArchive::new(File::open(file_path).unwrap()).unwrap();
+        let mut entries = Vec::new();
+        for idx in 0..archive.len() {
+            let mut entry = archive.by_index(idx).unwrap();
+            let mut content = String::new();
+            entry.read_to_string(&mut content).unwrap();
+            entries.push((entry.name().to_string(), content));
+        }
+
+        let mut zip = ZipWriter::new(File::create(file_path).unwrap());
+        for (name, content) in entries {
+            let content = if name == "xl/worksheets/sheet1.xml" {
+                assert!(content.contains(from), "{from} not found in {content}");
+                content.replace(from, to)
+            } else {
+                content
+            };
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn entry(file_path: &str, options: &str) -> XlsxFileEntry {
+        toml::from_str(&format!(
+            "file_path = {file_path:?}\nsheet_name = \"Transactions\"\n{options}"
+        ))
+        .expect("Valid entry in tests.")
+    }
+
+    #[test]
+    fn positional_columns_with_date_and_text_cells() {
+        let file_path = workbook(
+            "positional.xlsx",
+            &[
+                vec![Text("No."), Text("Date")],
+                vec![
+                    Number(1.0),
+                    Date(2024, 1, 5),
+                    Text("Buying"),
+                    Text("EUR"),
+                    Number(100.1),
+                    Text("BTC"),
+                    Number(0.1),
+                ],
+                vec![
+                    Number(2.0),
+                    Text("06.01.2024 10:30:00"),
+                    Text("Selling"),
+                    Text("BTC"),
+                    Text("0,1"),
+                    Text("EUR"),
+                    Text("1.234,5"),
+                    Text("Kraken"),
+                ],
+            ],
+        )
+        .unwrap();
+        let entry = entry(
+            &file_path,
+            r#"
+            start_row = 1
+            account_column = "H"
+            date_formats = ["%d.%m.%Y %H:%M:%S"]
+            decimal_separator = ","
+            thousands_separator = "."
+            timezone = "Europe/Berlin"
+            "#,
+        );
+        let transactions = parse_xlsx_file(&entry).unwrap();
+        assert_eq!(transactions.len(), 2);
+
+        // Numeric cells are read from their raw text, without the `f64` conversion.
+        let buy = &transactions[0];
+        assert_eq!(buy.ordinal(), 1);
+        assert_eq!(buy.date(), date("2024-01-05"));
+        assert_eq!(buy.timestamp(), None);
+        assert_eq!(buy.input(), (asset("EUR"), dec("100.1")));
+        assert_eq!(buy.output(), (asset("BTC"), dec("0.1")));
+        assert_eq!(buy.account(), None);
+
+        let sale = &transactions[1];
+        assert_eq!(sale.ordinal(), 2);
+        assert_eq!(sale.date(), date("2024-01-06"));
+        assert_eq!(
+            sale.timestamp().map(|timestamp| timestamp.to_rfc3339()),
+            Some("2024-01-06T09:30:00+00:00".to_string())
+        );
+        assert_eq!(sale.input(), (asset("BTC"), dec("0.1")));
+        assert_eq!(sale.output(), (asset("EUR"), dec("1234.5")));
+        assert_eq!(sale.account(), Some("Kraken"));
+    }
+
+    #[test]
+    fn columns_mapped_by_headers() {
+        let file_path = workbook(
+            "headers.xlsx",
+            &[
+                vec![Text("Exported transactions")],
+                vec![
+                    Text("Token Out"),
+                    Text("Amount Out"),
+                    Text("Date"),
+                    Text("Type"),
+                    Text("Token In"),
+                    Text("Amount In"),
+                ],
+                vec![
+                    Text("BTC"),
+                    Number(0.5),
+                    Date(2024, 2, 1),
+                    Text("Buying"),
+                    Text("EUR"),
+                    Number(20000.0),
+                ],
+            ],
+        )
+        .unwrap();
+        let entry = entry(
+            &file_path,
+            r#"
+            start_row = 2
+            [columns]
+            tx_type = "type"
+            input_type = "Token In"
+            input_amount = "Amount In"
+            output_type = "Token Out"
+            output_amount = "Amount Out"
+            "#,
+        );
+        let transactions = parse_xlsx_file(&entry).unwrap();
+
+        assert_eq!(transactions.len(), 1);
+        let buy = &transactions[0];
+        // Ordinal isn't mapped, so the row order is used.
+        assert_eq!(buy.ordinal(), 1);
+        assert_eq!(buy.date(), date("2024-02-01"));
+        assert_eq!(buy.input(), (asset("EUR"), dec("20000")));
+        assert_eq!(buy.output(), (asset("BTC"), dec("0.5")));
+        assert_eq!(
+            buy.location(),
+            &SourceLocation::new(file_path.split('/').next_back().unwrap())
+                .with_sheet("Transactions")
+                .with_row(3)
+        );
+    }
+
+    #[test]
+    fn raw_values_are_aligned_with_the_used_range() {
+        // Used range starts at `B2`, so its positions are offset from the sheet positions.
+        let file_path = workbook(
+            "offset.xlsx",
+            &[
+                vec![],
+                vec![Blank, Text("No.")],
+                vec![
+                    Blank,
+                    Number(1.0),
+                    Date(2024, 1, 5),
+                    Text("Buying"),
+                    Text("EUR"),
+                    Number(100.25),
+                    Text("ETH"),
+                    Number(0.5),
+                ],
+                vec![
+                    Blank,
+                    Number(2.0),
+                    Date(2024, 1, 6),
+                    Text("Selling"),
+                    Text("ETH"),
+                    Number(0.25),
+                    Text("EUR"),
+                    Number(80.0),
+                ],
+            ],
+        )
+        .unwrap();
+        patch_sheet(
+            &file_path,
+            "<v>0.5</v>",
+            "<v>0.500000000000000000123456789</v>",
+        );
+        let transactions = parse_xlsx_file(&entry(&file_path, "start_row = 1")).unwrap();
+        assert_eq!(transactions.len(), 2);
+
+        // Value with more digits than `f64` can hold is taken from the raw text.
+        let buy = &transactions[0];
+        assert_eq!(buy.input(), (asset("EUR"), dec("100.25")));
+        assert_eq!(
+            buy.output(),
+            (asset("ETH"), dec("0.500000000000000000123456789"))
+        );
+
+        let sale = &transactions[1];
+        assert_eq!(sale.input(), (asset("ETH"), dec("0.25")));
+        assert_eq!(sale.output(), (asset("EUR"), dec("80")));
+    }
+
+    #[test]
+    fn data_after_the_first_empty_row_is_rejected() {
+        let row = |ordinal: f64, day: u8| {
+            vec![
+                Number(ordinal),
+                Date(2024, 1, day),
+                Text("Buying"),
+                Text("EUR"),
+                Number(100.0),
+                Text("BTC"),
+                Number(1.0),
+            ]
+        };
+        let file_path = workbook(
+            "gap.xlsx",
+            &[vec![Text("No.")], row(1.0, 1), vec![], row(2.0, 2)],
+        )
+        .unwrap();
+        let error = parse_xlsx_file(&entry(&file_path, "start_row = 1")).unwrap_err();
+
+        // Row following the empty one is reported.
+        assert!(matches!(
+            error,
+            ParseError::DataAfterEnd { location } if location.row == Some(4)
+        ));
+    }
 }
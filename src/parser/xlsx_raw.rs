@@ -0,0 +1,276 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reader of the raw numeric cell values, directly from the XLSX XML.
+//!
+//! `calamine` converts numeric cells into `f64`, which rounds the amounts with many decimals
+//! (e.g. 18 decimals for ETH). The XML however stores the value as text, which is parsed without that conversion.
+//! Note that Excel writes at most 17 significant digits, so the text is only as precise as the tool which saved it.
+
+use quick_xml::{events::Event, reader::Reader};
+use std::{collections::HashMap, fs::File, io::BufReader, io::Read};
+use zip::ZipArchive;
+
+use super::columns::column_index;
+
+/// Raw text of the numeric cells of a single sheet, keyed by the zero-based (row, column) position.
+#[derive(Debug, Default)]
+pub struct RawSheetValues {
+    values: HashMap<(u32, u32), String>,
+}
+
+impl RawSheetValues {
+    /// Load the raw numeric cell values of the sheet.
+    pub fn load(file_path: &str, sheet_name: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut archive = ZipArchive::new(BufReader::new(File::open(file_path)?))?;
+
+        let sheet_path = sheet_path(&mut archive, sheet_name)?;
+        let sheet_xml = read_entry(&mut archive, &sheet_path)?;
+
+        let mut reader = Reader::from_str(&sheet_xml);
+        let mut values = HashMap::new();
+
+        // Position of the current row & cell, used in case the cell reference is missing.
+        let mut row_idx: u32 = 0;
+        let mut col_idx: u32 = 0;
+        // Position of the numeric cell whose value is being read, if any.
+        let mut numeric_cell: Option<(u32, u32)> = None;
+        let mut in_value = false;
+
+        loop {
+            match reader.read_event()? {
+                Event::Start(e) | Event::Empty(e) => match e.local_name().as_ref() {
+                    b"row" => {
+                        if let Some(r) = attribute(&e, b"r")? {
+                            row_idx = r.parse::<u32>()?.saturating_sub(1);
+                        }
+                        col_idx = 0;
+                    }
+                    b"c" => {
+                        if let Some((row, col)) =
+                            attribute(&e, b"r")?.and_then(|r| cell_position(&r))
+                        {
+                            row_idx = row;
+                            col_idx = col;
+                        }
+
+                        // Only numeric cells are of interest, their type is either missing or `n`.
+                        let is_numeric =
+                            matches!(attribute(&e, b"t")?.as_deref(), None | Some("n"));
+                        numeric_cell = is_numeric.then_some((row_idx, col_idx));
+                        col_idx += 1;
+                    }
+                    b"v" => in_value = numeric_cell.is_some(),
+                    _ => {}
+                },
+                Event::Text(text) if in_value => {
+                    if let Some(position) = numeric_cell {
+                        values.insert(position, text.unescape()?.trim().to_string());
+                    }
+                }
+                Event::End(e) => match e.local_name().as_ref() {
+                    b"v" => in_value = false,
+                    b"c" => numeric_cell = None,
+                    b"row" => row_idx += 1,
+                    _ => {}
+                },
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        Ok(RawSheetValues { values })
+    }
+
+    /// Raw text of the numeric cell at the zero-based (row, column) position, if available.
+    pub fn get(&self, row: usize, col: usize) -> Option<&str> {
+        self.values
+            .get(&(row as u32, col as u32))
+            .map(String::as_str)
+    }
+}
+
+/// Path of the sheet XML inside the archive.
+fn sheet_path<R: Read + std::io::Seek>(
+    archive: &mut ZipArchive<R>,
+    sheet_name: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    // 1. Find the relationship Id of the sheet in the workbook.
+    let workbook = read_entry(archive, "xl/workbook.xml")?;
+    let mut reader = Reader::from_str(&workbook);
+    let mut relationship_id = None;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e)
+                if e.local_name().as_ref() == b"sheet"
+                    && attribute(&e, b"name")?.as_deref() == Some(sheet_name) =>
+            {
+                relationship_id = attribute(&e, b"id")?;
+                break;
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    let relationship_id =
+        relationship_id.ok_or_else(|| format!("Sheet '{sheet_name}' not found"))?;
+
+    // 2. Find the target of the relationship.
+    let relationships = read_entry(archive, "xl/_rels/workbook.xml.rels")?;
+    let mut reader = Reader::from_str(&relationships);
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e)
+                if e.local_name().as_ref() == b"Relationship"
+                    && attribute(&e, b"Id")?.as_deref() == Some(relationship_id.as_str()) =>
+            {
+                let target =
+                    attribute(&e, b"Target")?.ok_or("Sheet relationship is missing the target")?;
+
+                return Ok(match target.strip_prefix('/') {
+                    Some(absolute) => absolute.to_string(),
+                    None => format!("xl/{target}"),
+                });
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Err(format!("Sheet '{sheet_name}' relationship '{relationship_id}' not found").into())
+}
+
+/// Read the archive entry as a string.
+fn read_entry<R: Read + std::io::Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut content = String::new();
+    archive.by_name(name)?.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+/// Value of the attribute with the specified local name, if present.
+fn attribute(
+    element: &quick_xml::events::BytesStart,
+    name: &[u8],
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    for attribute in element.attributes() {
+        let attribute = attribute?;
+        if attribute.key.local_name().as_ref() == name {
+            let value = std::str::from_utf8(&attribute.value)?;
+            return Ok(Some(quick_xml::escape::unescape(value)?.into_owned()));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Convert the cell reference (e.g. `B12`) into the zero-based (row, column) position.
+fn cell_position(reference: &str) -> Option<(u32, u32)> {
+    let split = reference.find(|c: char| c.is_ascii_digit())?;
+    let (column, row) = reference.split_at(split);
+
+    let row = row.parse::<u32>().ok()?.checked_sub(1)?;
+    let column = column_index(column)? as u32;
+
+    Some((row, column))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_path;
+
+    use std::io::Write;
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    /// Write a minimal workbook with a single `Data` sheet, and return its path.
+    fn workbook(name: &str, sheet_data: &str) -> String {
+        let path = temp_path(name);
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        let entries = [
+            (
+                "xl/workbook.xml",
+                r#"<workbook xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships">
+                <sheets><sheet name="Data" sheetId="1" r:id="rId1"/></sheets></workbook>"#
+                    .to_string(),
+            ),
+            (
+                "xl/_rels/workbook.xml.rels",
+                r#"<Relationships><Relationship Id="rId1" Target="worksheets/sheet1.xml"/></Relationships>"#
+                    .to_string(),
+            ),
+            (
+                "xl/worksheets/sheet1.xml",
+                format!("<worksheet><sheetData>{sheet_data}</sheetData></worksheet>"),
+            ),
+        ];
+        for (entry, content) in entries {
+            zip.start_file(entry, SimpleFileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn numeric_values_are_kept_as_text() {
+        let path = workbook(
+            "raw-values.xlsx",
+            r#"<row r="2">
+                <c r="B2" t="s"><v>0</v></c>
+                <c r="C2"><v>0.123456789012345678</v></c>
+                <c r="D2" t="n"><v> 1E-18 </v></c>
+                <c r="E2" t="inlineStr"><is><t>12.5</t></is></c>
+                <c r="F2" t="str"><f>A1</f><v>7</v></c>
+            </row>"#,
+        );
+        let values = RawSheetValues::load(&path, "Data").unwrap();
+
+        // More digits than `f64` can hold are kept.
+        assert_eq!(values.get(1, 2), Some("0.123456789012345678"));
+        assert_eq!(values.get(1, 3), Some("1E-18"));
+
+        // Shared strings, inline strings & formula strings aren't numeric.
+        assert_eq!(values.get(1, 1), None);
+        assert_eq!(values.get(1, 4), None);
+        assert_eq!(values.get(1, 5), None);
+        assert_eq!(values.get(0, 2), None);
+    }
+
+    #[test]
+    fn missing_references_follow_the_previous_cell() {
+        let path = workbook(
+            "raw-positions.xlsx",
+            r#"<row r="3"><c r="C3"><v>1</v></c><c><v>2</v></c></row>
+               <row><c><v>3</v></c><c r="AA4"><v>4</v></c></row>"#,
+        );
+        let values = RawSheetValues::load(&path, "Data").unwrap();
+
+        assert_eq!(values.get(2, 2), Some("1"));
+        assert_eq!(values.get(2, 3), Some("2"));
+        assert_eq!(values.get(3, 0), Some("3"));
+        assert_eq!(values.get(3, 26), Some("4"));
+    }
+
+    #[test]
+    fn unknown_sheet_is_an_error() {
+        let path = workbook("raw-sheets.xlsx", "");
+
+        let error = RawSheetValues::load(&path, "Transactions").unwrap_err();
+        assert_eq!(error.to_string(), "Sheet 'Transactions' not found");
+    }
+}
@@ -0,0 +1,218 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Local table of daily asset prices, expressed in the base fiat currency.
+//!
+//! For fiat currencies, the price is the exchange rate into the base currency.
+//! E.g. if 1 USD is worth 0.92 EUR, the entry for USD is **0.92**.
+//!
+//! # Supported formats
+//!
+//! CSV file (`,` or `;` delimited) with a header row and columns in order `date`, `asset`, `price`:
+//!
+//! ```csv
+//! date,asset,price
+//! 2024-01-02,USD,0.9123
+//! ```
+//!
+//! TOML file, with the file name ending in `.toml`:
+//!
+//! ```toml
+//! [[prices]]
+//! date = "2024-01-02"
+//! asset = "USD"
+//! price = "0.9123"
+//! ```
+
+use crate::types::{AssetType, Transaction};
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+};
+
+/// Date format used in the price files.
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Table of daily asset prices.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct PriceTable {
+    prices: HashMap<AssetType, BTreeMap<NaiveDate, Decimal>>,
+}
+
+impl PriceTable {
+    /// Load the price table from a CSV or TOML file.
+    /// Format is determined by the file extension, TOML for `.toml`, CSV otherwise.
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = std::fs::read_to_string(path)?;
+
+        let entries = if path.to_lowercase().ends_with(".toml") {
+            #[derive(Deserialize)]
+            struct PriceFile {
+                prices: Vec<PriceEntry>,
+            }
+
+            toml::from_str::<PriceFile>(&content)?.prices
+        } else {
+            let delimiter = if content.lines().next().unwrap_or_default().contains(';') {
+                b';'
+            } else {
+                b','
+            };
+
+            csv::ReaderBuilder::new()
+                .delimiter(delimiter)
+                .trim(csv::Trim::All)
+                .from_reader(content.as_bytes())
+                .deserialize::<PriceEntry>()
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        let mut table = PriceTable::default();
+        for (idx, entry) in entries.into_iter().enumerate() {
+            let date = NaiveDate::parse_from_str(&entry.date, DATE_FORMAT).map_err(|e| {
+                format!(
+                    "File: '{path}', entry {}; Invalid date '{}', expected format YYYY-MM-DD: {e}",
+                    idx + 1,
+                    entry.date
+                )
+            })?;
+            let price = Decimal::from_str(&entry.price).map_err(|e| {
+                format!(
+                    "File: '{path}', entry {}; Invalid price '{}': {e}",
+                    idx + 1,
+                    entry.price
+                )
+            })?;
+
+            table.insert(AssetType::from(entry.asset), date, price);
+        }
+
+        log::debug!(
+            "Loaded prices for {} assets from file: {}",
+            table.prices.len(),
+            path
+        );
+
+        Ok(table)
+    }
+
+    /// Insert the price of the asset on the given date.
+    pub fn insert(&mut self, asset: AssetType, date: NaiveDate, price: Decimal) {
+        self.prices.entry(asset).or_default().insert(date, price);
+    }
+
+    /// Price of the asset on the given date.
+    ///
+    /// If there's no price for the exact date (e.g. weekends for exchange rates),
+    /// the most recent price before it is used.
+    pub fn price(&self, asset: &AssetType, date: NaiveDate) -> Option<Decimal> {
+        self.prices
+            .get(asset)?
+            .range(..=date)
+            .next_back()
+            .map(|(_, price)| *price)
+    }
+
+    /// Market value of the transaction, on its date.
+    ///
+    /// Output asset is valued if its price is known, input asset otherwise.
+    pub fn market_value(&self, transaction: &Transaction) -> Option<Decimal> {
+        let (input_token, input_amount) = transaction.input();
+        let (output_token, output_amount) = transaction.output();
+        let date = transaction.date();
+
+        self.price(&output_token, date)
+            .map(|price| price * output_amount)
+            .or_else(|| {
+                self.price(&input_token, date)
+                    .map(|price| price * input_amount)
+            })
+    }
+}
+
+/// Single entry of the price file.
+#[derive(Debug, Deserialize)]
+struct PriceEntry {
+    /// Date of the price, in the `YYYY-MM-DD` format.
+    date: String,
+    /// Asset which is priced.
+    asset: String,
+    /// Price of one unit of the asset, in the base currency.
+    #[serde(alias = "rate")]
+    price: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{asset, date, dec, temp_file};
+
+    #[test]
+    fn latest_price_on_or_before_the_date_is_used() {
+        let mut table = PriceTable::default();
+        table.insert(asset("USD"), date("2024-01-02"), dec("0.91"));
+        table.insert(asset("USD"), date("2024-01-05"), dec("0.93"));
+
+        let usd = asset("USD");
+        assert_eq!(table.price(&usd, date("2024-01-01")), None);
+        assert_eq!(table.price(&usd, date("2024-01-02")), Some(dec("0.91")));
+        assert_eq!(table.price(&usd, date("2024-01-04")), Some(dec("0.91")));
+        assert_eq!(table.price(&usd, date("2024-01-05")), Some(dec("0.93")));
+        assert_eq!(table.price(&usd, date("2024-12-31")), Some(dec("0.93")));
+        assert_eq!(table.price(&asset("GBP"), date("2024-01-05")), None);
+    }
+
+    #[test]
+    fn csv_and_toml_files_are_loaded() {
+        let mut expected = PriceTable::default();
+        expected.insert(asset("USD"), date("2024-01-02"), dec("0.9123"));
+        expected.insert(asset("BTC"), date("2024-01-03"), dec("40000"));
+
+        let csv = temp_file(
+            "prices.csv",
+            "date;asset;rate\n2024-01-02;USD;0.9123\n2024-01-03;BTC;40000\n",
+        );
+        assert_eq!(PriceTable::from_file(&csv).unwrap(), expected);
+
+        let toml = temp_file(
+            "prices.toml",
+            r#"
+[[prices]]
+date = "2024-01-02"
+asset = "USD"
+price = "0.9123"
+
+[[prices]]
+date = "2024-01-03"
+asset = "BTC"
+price = "40000"
+"#,
+        );
+        assert_eq!(PriceTable::from_file(&toml).unwrap(), expected);
+    }
+
+    #[test]
+    fn invalid_date_names_the_entry() {
+        let path = temp_file(
+            "invalid-prices.csv",
+            "date,asset,price\n02.01.2024,USD,0.91\n",
+        );
+
+        let error = PriceTable::from_file(&path).unwrap_err().to_string();
+        assert!(error.contains("entry 1"), "{error}");
+        assert!(error.contains("02.01.2024"), "{error}");
+    }
+}
@@ -0,0 +1,169 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! JSON & NDJSON export of the full ledger state.
+//!
+//! Numeric fields are kept as decimal strings to avoid any loss of precision,
+//! and dates are written in the ISO 8601 format.
+
+use crate::{
+    fifo::{InventoryItem, Ledger, YearlyReport},
+    types::Transaction,
+};
+
+use serde::Serialize;
+use std::io::Write;
+
+/// Single record of the NDJSON export, tagged with its kind in the `record` field.
+#[derive(Debug, Serialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+pub enum LedgerRecord<'a> {
+    /// Processed transaction, in the base currency.
+    Transaction(&'a Transaction),
+    /// Inventory item (fragment of a lot) created by the transaction.
+    InventoryItem(&'a InventoryItem),
+    /// Income & loss report of a single (fiscal) year.
+    YearlyReport(&'a YearlyReport),
+}
+
+/// Full ledger state, i.e. the transactions, all inventory items & the yearly reports.
+#[derive(Debug, Serialize)]
+pub struct JsonReport<'a> {
+    /// Processed transactions, in the processing order.
+    transactions: &'a [Transaction],
+    /// Inventory items, ordered by the ordinal of the transaction which created them.
+    inventory: &'a [&'a InventoryItem],
+    /// Yearly income & loss reports.
+    yearly_reports: Vec<YearlyReport>,
+}
+
+impl<'a> JsonReport<'a> {
+    /// Create a new `JsonReport` instance, capturing the state of the ledger.
+    pub fn new(ledger: &'a Ledger<'a>) -> Self {
+        JsonReport {
+            transactions: ledger.transactions(),
+            inventory: ledger.in_order(),
+            yearly_reports: ledger.yearly_reports(),
+        }
+    }
+
+    /// Records of the ledger state: all transactions, followed by the inventory items & the yearly reports.
+    pub fn records(&self) -> impl Iterator<Item = LedgerRecord<'_>> {
+        self.transactions
+            .iter()
+            .map(LedgerRecord::Transaction)
+            .chain(
+                self.inventory
+                    .iter()
+                    .map(|item| LedgerRecord::InventoryItem(item)),
+            )
+            .chain(self.yearly_reports.iter().map(LedgerRecord::YearlyReport))
+    }
+
+    /// Write the ledger state as a single, pretty-printed JSON document.
+    pub fn write_json(&self, mut writer: impl Write) -> Result<(), serde_json::Error> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer
+            .write_all(b"\n")
+            .and_then(|_| writer.flush())
+            .map_err(serde_json::Error::io)
+    }
+
+    /// Write the ledger state as NDJSON, one record per line.
+    pub fn write_ndjson(&self, mut writer: impl Write) -> Result<(), serde_json::Error> {
+        for record in self.records() {
+            serde_json::to_writer(&mut writer, &record)?;
+            writer.write_all(b"\n").map_err(serde_json::Error::io)?;
+        }
+        writer.flush().map_err(serde_json::Error::io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fifo::LedgerSettings,
+        test_utils::tx,
+        types::TransactionType::{Buying, Selling},
+    };
+
+    use serde_json::{json, Value};
+
+    fn ledger<'a>() -> Ledger<'a> {
+        let transactions = vec![
+            tx(1, "2024-01-05", Buying, ("EUR", "100.10"), ("BTC", "0.1")),
+            tx(2, "2024-02-10", Selling, ("BTC", "0.1"), ("EUR", "150")),
+        ];
+        Ledger::new(transactions, LedgerSettings::default(), None).unwrap()
+    }
+
+    #[test]
+    fn json_document_holds_the_full_state() {
+        let ledger = ledger();
+        let mut output = Vec::new();
+        JsonReport::new(&ledger).write_json(&mut output).unwrap();
+
+        assert!(output.ends_with(b"}\n"));
+        let document: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(document["transactions"].as_array().unwrap().len(), 2);
+        assert_eq!(document["inventory"].as_array().unwrap().len(), 2);
+
+        // Decimals are kept exactly, as strings.
+        let buy = &document["transactions"][0];
+        assert_eq!(buy["date"], json!("2024-01-05"));
+        assert_eq!(buy["input_amount"], json!("100.10"));
+
+        let sale = &document["inventory"][1];
+        assert_eq!(sale["lot_id"], json!(2));
+        assert_eq!(sale["parent_lot"], json!(1));
+        assert_eq!(sale["acquisition_date"], json!("2024-01-05"));
+        assert_eq!(sale["cost_basis"], json!("1001.0"));
+
+        let report = &document["yearly_reports"][0];
+        assert_eq!(report["label"], json!("2024"));
+        assert_eq!(report["sell_income"], json!("150.0"));
+        assert_eq!(report["holding_period_gains"], Value::Null);
+    }
+
+    #[test]
+    fn ndjson_has_one_tagged_record_per_line() {
+        let ledger = ledger();
+        let mut output = Vec::new();
+        JsonReport::new(&ledger).write_ndjson(&mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.ends_with('\n'));
+        let records: Vec<Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        let kinds: Vec<_> = records
+            .iter()
+            .map(|record| record["record"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                "transaction",
+                "transaction",
+                "inventory_item",
+                "inventory_item",
+                "yearly_report"
+            ]
+        );
+        assert_eq!(records[1]["ordinal"], json!(2));
+        assert_eq!(records[3]["sale_price"], json!("1500"));
+        assert_eq!(records[4]["expense"], json!("100.10"));
+    }
+}
@@ -0,0 +1,456 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! XLSX report, with one sheet per section.
+//!
+//! Numbers are written as numeric cells & dates as date cells, so the report can be used in Excel as is.
+//! Amounts of the assets use the general number format, to show all of their decimals,
+//! while the values in the base currency are formatted with two decimals.
+
+use crate::{
+    fifo::{OpenLot, OpenLotsTotal, YearlyReport},
+    types::{AssetType, CsvHelper, CsvLineData, FiscalYear},
+    uk::{Section104Pool, UkDisposal, UkTaxYearReport},
+};
+
+use chrono::NaiveDate;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use rust_xlsxwriter::{Format, FormatAlign, Workbook, XlsxError};
+use std::{borrow::Cow, str::FromStr};
+
+/// Date format used by the `CsvLineData`.
+const CSV_DATE_FORMAT: &str = "%d.%m.%Y";
+
+/// Value of a single cell in the report.
+enum Cell {
+    /// Plain text.
+    Text(String),
+    /// Whole number, e.g. the ordinal of the transaction.
+    Integer(u32),
+    /// Amount of an asset.
+    Amount(Decimal),
+    /// Value in the base currency.
+    Value(Decimal),
+    /// Date, without the time.
+    Date(NaiveDate),
+    /// Empty cell.
+    Empty,
+}
+
+impl Cell {
+    /// Text cell, or an empty cell if the text is missing or empty.
+    fn text(text: Option<impl Into<String>>) -> Self {
+        match text.map(Into::into) {
+            Some(text) if !text.is_empty() => Cell::Text(text),
+            _ => Cell::Empty,
+        }
+    }
+
+    /// Integer cell, or an amount if the number isn't a valid `u32`.
+    fn integer(number: Decimal) -> Self {
+        Some(number)
+            .filter(|number| number.fract().is_zero())
+            .and_then(|number| number.to_u32())
+            .map_or(Cell::Amount(number), Cell::Integer)
+    }
+
+    /// Parse the number of the `CsvLineData` field, using the provided cell type.
+    /// Falls back to the text cell in case the field isn't a number.
+    fn number(field: Option<Cow<'_, str>>, cell: fn(Decimal) -> Cell) -> Self {
+        match field {
+            Some(field) => Decimal::from_str(&field)
+                .map(cell)
+                .unwrap_or_else(|_| Cell::text(Some(field))),
+            None => Cell::Empty,
+        }
+    }
+
+    /// Parse the date of the `CsvLineData` field.
+    /// Falls back to the text cell in case the field isn't a date.
+    fn date(field: Cow<'_, str>) -> Self {
+        NaiveDate::parse_from_str(&field, CSV_DATE_FORMAT)
+            .map(Cell::Date)
+            .unwrap_or_else(|_| Cell::text(Some(field)))
+    }
+}
+
+/// XLSX workbook with the report sheets, written to the file once all sheets are added.
+pub struct XlsxReport {
+    workbook: Workbook,
+    /// Format of the header row.
+    header_format: Format,
+    /// Format of the date cells.
+    date_format: Format,
+    /// Format of the values in the base currency.
+    value_format: Format,
+}
+
+impl XlsxReport {
+    /// Create a new, empty `XlsxReport` instance.
+    pub fn new() -> Self {
+        XlsxReport {
+            workbook: Workbook::new(),
+            header_format: Format::new().set_bold().set_align(FormatAlign::Center),
+            date_format: Format::new().set_num_format("dd.mm.yyyy"),
+            value_format: Format::new().set_num_format("#,##0.00"),
+        }
+    }
+
+    /// Add the sheet with the FIFO breakdown, one row per inventory item.
+    ///
+    /// # Arguments
+    /// * `lines` - Lines of the breakdown, e.g. from the `Ledger::csv_line_iter`.
+    pub fn add_fifo_breakdown<T: CsvLineData>(
+        &mut self,
+        lines: impl Iterator<Item = T>,
+    ) -> Result<(), XlsxError> {
+        let rows = lines.map(|line| {
+            vec![
+                Cell::number(Some(line.ordinal()), Cell::integer),
+                Cell::date(line.transaction_date()),
+                Cell::date(line.acquisition_date()),
+                Cell::text(Some(line.action())),
+                Cell::text(Some(line.input_type())),
+                Cell::number(Some(line.input_amount()), Cell::Amount),
+                Cell::text(Some(line.output_type())),
+                Cell::number(Some(line.output_amount()), Cell::Amount),
+                Cell::number(line.income_amount(), Cell::Value),
+                Cell::number(line.expense_amount(), Cell::Value),
+                Cell::number(line.profit(), Cell::Value),
+                Cell::number(line.fee(), Cell::Value),
+                Cell::text(line.holding_period()),
+                Cell::number(line.wash_sale_adjustment(), Cell::Value),
+                Cell::text(line.account()),
+                Cell::number(line.lot_id(), Cell::integer),
+                Cell::number(line.parent_lot(), Cell::integer),
+            ]
+        });
+
+        self.add_sheet("FIFO Breakdown", &CsvHelper::<T>::HEADER_ELEMENTS, rows)
+    }
+
+    /// Add the sheet with the yearly income & loss reports, one row per year.
+    ///
+    /// Holding period & wash sale columns are only added if any report includes them.
+    pub fn add_yearly_reports(&mut self, reports: &[YearlyReport]) -> Result<(), XlsxError> {
+        let has_holding_period = reports
+            .iter()
+            .any(|report| report.holding_period_gains.is_some());
+        let has_wash_sales = reports
+            .iter()
+            .any(|report| report.disallowed_loss.is_some());
+
+        let mut header = vec![
+            "Year",
+            "Sell Income",
+            "Interest Income",
+            "Expense",
+            "Profit",
+            "Fees",
+        ];
+        if has_holding_period {
+            header.extend(["Short-Term Gain", "Long-Term Gain", "Exempt Gain"]);
+        }
+        if has_wash_sales {
+            header.push("Disallowed Wash Sale Loss");
+        }
+
+        let rows = reports.iter().map(|report| {
+            let mut row = vec![
+                Cell::Text(report.label.clone()),
+                Cell::Value(report.sell_income),
+                Cell::Value(report.interest_income),
+                Cell::Value(report.expense),
+                Cell::Value(report.profit()),
+                Cell::Value(report.fees),
+            ];
+            if has_holding_period {
+                let gains = report.holding_period_gains.clone().unwrap_or_default();
+                row.extend([
+                    Cell::Value(gains.short_term),
+                    Cell::Value(gains.long_term),
+                    Cell::Value(gains.exempt),
+                ]);
+            }
+            if has_wash_sales {
+                row.push(Cell::Value(report.disallowed_loss.unwrap_or_default()));
+            }
+            row
+        });
+
+        self.add_sheet("Yearly Summary", &header, rows)
+    }
+
+    /// Add the sheet with the open lots, one row per lot.
+    pub fn add_open_lots(&mut self, lots: &[OpenLot]) -> Result<(), XlsxError> {
+        let rows = lots.iter().map(|lot| {
+            vec![
+                Cell::Text(lot.asset.to_string()),
+                Cell::text(lot.account.clone()),
+                Cell::Date(lot.acquisition_date),
+                Cell::Integer(lot.ordinal),
+                Cell::Amount(lot.remaining_amount),
+                Cell::Value(lot.cost_basis),
+                Cell::Value(lot.total_cost()),
+            ]
+        });
+
+        self.add_sheet("Open Lots", &OpenLot::CSV_HEADER_ELEMENTS, rows)
+    }
+
+    /// Add the sheet with the totals of the open lots, one row per asset.
+    pub fn add_asset_totals(&mut self, totals: &[OpenLotsTotal]) -> Result<(), XlsxError> {
+        let rows = totals.iter().map(|total| {
+            vec![
+                Cell::Text(total.asset.to_string()),
+                Cell::Amount(total.amount),
+                Cell::Value(total.total_cost),
+            ]
+        });
+
+        self.add_sheet("Asset Totals", &["Asset", "Amount", "Total Cost"], rows)
+    }
+
+    /// Add the sheet with the disposals matched by the UK share pooling rules, one row per matched part.
+    pub fn add_uk_disposals(&mut self, disposals: &[UkDisposal]) -> Result<(), XlsxError> {
+        let rows = disposals.iter().map(|disposal| {
+            vec![
+                match disposal.ordinals[..] {
+                    [ordinal] => Cell::Integer(ordinal),
+                    _ => Cell::Text(disposal.ordinals_label()),
+                },
+                Cell::Date(disposal.date),
+                Cell::Text(
+                    FiscalYear::UK_TAX_YEAR
+                        .label(FiscalYear::UK_TAX_YEAR.start_year(disposal.date)),
+                ),
+                Cell::Text(disposal.asset.to_string()),
+                Cell::Amount(disposal.amount),
+                Cell::Text(disposal.rule.to_string()),
+                disposal.acquisition_date.map_or(Cell::Empty, Cell::Date),
+                Cell::Value(disposal.proceeds),
+                Cell::Value(disposal.allowable_cost),
+                Cell::Value(disposal.gain()),
+            ]
+        });
+
+        self.add_sheet("UK Disposals", &UkDisposal::CSV_HEADER_ELEMENTS, rows)
+    }
+
+    /// Add the sheet with the Section 104 pools, one row per asset.
+    pub fn add_uk_pools<'p>(
+        &mut self,
+        pools: impl Iterator<Item = (&'p AssetType, &'p Section104Pool)>,
+    ) -> Result<(), XlsxError> {
+        let rows = pools.map(|(asset, pool)| {
+            vec![
+                Cell::Text(asset.to_string()),
+                Cell::Amount(pool.amount),
+                Cell::Value(pool.cost),
+                pool.unit_cost().map_or(Cell::Empty, Cell::Value),
+            ]
+        });
+
+        self.add_sheet(
+            "Section 104 Pools",
+            &Section104Pool::CSV_HEADER_ELEMENTS,
+            rows,
+        )
+    }
+
+    /// Add the sheet with the UK tax year reports, one row per tax year.
+    pub fn add_uk_tax_years(&mut self, reports: &[UkTaxYearReport]) -> Result<(), XlsxError> {
+        let header = [
+            "Tax Year",
+            "Disposals",
+            "Proceeds",
+            "Allowable Costs",
+            "Gains",
+            "Losses",
+            "Net Gain",
+            "Interest Income",
+        ];
+        let rows = reports.iter().map(|report| {
+            vec![
+                Cell::Text(FiscalYear::UK_TAX_YEAR.label(report.start_year)),
+                Cell::Integer(report.disposals as u32),
+                Cell::Value(report.proceeds),
+                Cell::Value(report.allowable_costs),
+                Cell::Value(report.gains),
+                Cell::Value(report.losses),
+                Cell::Value(report.net_gain()),
+                Cell::Value(report.interest_income),
+            ]
+        });
+
+        self.add_sheet("Tax Years", &header, rows)
+    }
+
+    /// Write the workbook to the file.
+    pub fn save(mut self, path: &str) -> Result<(), XlsxError> {
+        self.workbook.save(path)
+    }
+
+    /// Add a new sheet, with the header in the first row & the rows below it.
+    fn add_sheet(
+        &mut self,
+        name: &str,
+        header: &[&str],
+        rows: impl Iterator<Item = Vec<Cell>>,
+    ) -> Result<(), XlsxError> {
+        let sheet = self.workbook.add_worksheet();
+        sheet.set_name(name)?;
+
+        for (col, title) in header.iter().enumerate() {
+            sheet.write_string_with_format(0, col as u16, *title, &self.header_format)?;
+        }
+        sheet.set_freeze_panes(1, 0)?;
+
+        for (idx, row) in rows.enumerate() {
+            let row_idx = idx as u32 + 1;
+            for (col, cell) in row.into_iter().enumerate() {
+                let col = col as u16;
+                match cell {
+                    Cell::Text(text) => sheet.write_string(row_idx, col, text)?,
+                    Cell::Integer(number) => sheet.write_number(row_idx, col, number)?,
+                    // Excel can't store more digits than `f64` does, exact value is kept as text otherwise.
+                    Cell::Amount(amount) => match amount.to_f64() {
+                        Some(number) => sheet.write_number(row_idx, col, number)?,
+                        None => sheet.write_string(row_idx, col, amount.to_string())?,
+                    },
+                    Cell::Value(value) => match value.to_f64() {
+                        Some(number) => sheet.write_number_with_format(
+                            row_idx,
+                            col,
+                            number,
+                            &self.value_format,
+                        )?,
+                        None => sheet.write_string(row_idx, col, value.to_string())?,
+                    },
+                    Cell::Date(date) => {
+                        sheet.write_datetime_with_format(row_idx, col, date, &self.date_format)?
+                    }
+                    Cell::Empty => sheet,
+                };
+            }
+        }
+
+        sheet.autofit();
+        Ok(())
+    }
+}
+
+impl Default for XlsxReport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fifo::{Ledger, LedgerSettings},
+        test_utils::{date, dec, temp_path, tx},
+        types::TransactionType::{Buying, Selling},
+    };
+
+    use calamine::{open_workbook, Data, Reader, Xlsx};
+
+    /// Write the report & read all of its sheets back.
+    fn round_trip(report: XlsxReport, name: &str) -> Vec<(String, Vec<Vec<Data>>)> {
+        let path = temp_path(name);
+        report.save(path.to_str().unwrap()).unwrap();
+
+        let mut workbook: Xlsx<_> = open_workbook(&path).unwrap();
+        workbook
+            .worksheets()
+            .into_iter()
+            .map(|(name, range)| (name, range.rows().map(<[Data]>::to_vec).collect()))
+            .collect()
+    }
+
+    fn text(value: &str) -> Data {
+        Data::String(value.to_string())
+    }
+
+    #[test]
+    fn ledger_sheets_keep_the_cell_types() {
+        let transactions = vec![
+            tx(1, "2024-01-05", Buying, ("EUR", "100"), ("BTC", "0.125")),
+            tx(2, "2025-02-10", Selling, ("BTC", "0.125"), ("EUR", "150.5")),
+        ];
+        let ledger = Ledger::new(transactions, LedgerSettings::default(), None).unwrap();
+
+        let mut report = XlsxReport::new();
+        report.add_fifo_breakdown(ledger.csv_line_iter()).unwrap();
+        report.add_yearly_reports(&ledger.yearly_reports()).unwrap();
+        let sheets = round_trip(report, "ledger.xlsx");
+
+        let names: Vec<_> = sheets.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["FIFO Breakdown", "Yearly Summary"]);
+
+        let breakdown = &sheets[0].1;
+        assert_eq!(breakdown.len(), 3);
+        assert_eq!(breakdown[0][0], text("Ordinal"));
+        assert_eq!(breakdown[0].len(), 17);
+
+        let sale = &breakdown[2];
+        assert_eq!(sale[0], Data::Float(2.0));
+        let Data::DateTime(transaction_date) = &sale[1] else {
+            panic!("Expected a date cell, found {:?}", sale[1]);
+        };
+        assert_eq!(
+            transaction_date.as_datetime().unwrap().date(),
+            date("2025-02-10")
+        );
+        assert_eq!(sale[4], text("BTC"));
+        assert_eq!(sale[5], Data::Float(0.125));
+        assert_eq!(sale[6], text("EUR"));
+        // Income, expense & profit.
+        assert_eq!(sale[8..11], [150.5, 100.0, 50.5].map(Data::Float));
+        // Empty fee & holding period.
+        assert!(sale[11..13].iter().all(|cell| *cell == Data::Empty));
+
+        let yearly = &sheets[1].1;
+        assert_eq!(
+            yearly[0],
+            [
+                "Year",
+                "Sell Income",
+                "Interest Income",
+                "Expense",
+                "Profit",
+                "Fees"
+            ]
+            .map(text)
+        );
+        assert_eq!(yearly.len(), 3);
+        assert_eq!(yearly[2][0], text("2025"));
+        assert_eq!(yearly[2][4], Data::Float(50.5));
+    }
+
+    #[test]
+    fn numbers_fall_back_to_the_exact_text() {
+        assert!(matches!(Cell::integer(dec("7")), Cell::Integer(7)));
+        assert!(matches!(Cell::integer(dec("7.5")), Cell::Amount(amount) if amount == dec("7.5")));
+        assert!(matches!(
+            Cell::number(Some(Cow::Borrowed("n/a")), Cell::Value),
+            Cell::Text(text) if text == "n/a"
+        ));
+        assert!(matches!(Cell::number(None, Cell::Value), Cell::Empty));
+        assert!(matches!(
+            Cell::date(Cow::Borrowed("05.01.2024")),
+            Cell::Date(found) if found == date("2024-01-05")
+        ));
+    }
+}
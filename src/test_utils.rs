@@ -0,0 +1,74 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Helpers shared by the unit tests.
+
+use crate::{
+    fifo::{Ledger, LedgerSettings},
+    types::{AssetType, SourceLocation, Transaction, TransactionType},
+};
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use std::{path::PathBuf, str::FromStr};
+
+/// Parse the decimal number, e.g. `"1.5"`.
+pub(crate) fn dec(value: &str) -> Decimal {
+    Decimal::from_str(value).expect("Valid decimal in tests.")
+}
+
+/// Parse the date in the `YYYY-MM-DD` format.
+pub(crate) fn date(value: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").expect("Valid date in tests.")
+}
+
+/// Asset with the given name.
+pub(crate) fn asset(name: &str) -> AssetType {
+    AssetType::from(name.to_string())
+}
+
+/// Transaction exchanging the `input` for the `output`, both given as `(asset, amount)`.
+pub(crate) fn tx(
+    ordinal: u32,
+    date_str: &str,
+    tx_type: TransactionType,
+    input: (&str, &str),
+    output: (&str, &str),
+) -> Transaction {
+    Transaction::new(
+        ordinal,
+        date(date_str),
+        tx_type,
+        asset(input.0),
+        dec(input.1),
+        asset(output.0),
+        dec(output.1),
+        SourceLocation::new("test.csv").with_row(ordinal as usize + 1),
+    )
+}
+
+/// Ledger of the transactions, processed with the given settings.
+pub(crate) fn ledger<'a>(transactions: Vec<Transaction>, settings: LedgerSettings) -> Ledger<'a> {
+    Ledger::new(transactions, settings, None).expect("Transactions must be processable.")
+}
+
+/// Path of a file in the temporary directory, unique for the test process.
+pub(crate) fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("fifo-accounting-{}-{name}", std::process::id()))
+}
+
+/// Write the contents into a temporary file, and return its path.
+pub(crate) fn temp_file(name: &str, contents: &str) -> String {
+    let path = temp_path(name);
+    std::fs::write(&path, contents).expect("Temporary file must be writable.");
+    path.to_string_lossy().into_owned()
+}
@@ -10,9 +10,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use chrono::NaiveDate;
+use super::{FiatSettings, ParseError, SourceLocation};
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
 use rust_decimal::Decimal;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+
 use std::{
     fmt::{self, Display, Formatter},
     ops::Deref,
@@ -20,7 +23,7 @@ use std::{
 };
 
 /// Type of transactions that modify the balance of any asset in the 'ledger'.
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
 pub enum TransactionType {
     /// Invoice paid via crypto. Treated as if EUR was exchanged for the asset.
     Invoice,
@@ -32,6 +35,9 @@ pub enum TransactionType {
     Buying,
     /// Sell an asset for fiat.
     Selling,
+    /// Move an asset between own wallets or exchanges. Not a taxable event.
+    /// Difference between the input & output amount is treated as a fee paid in the transferred asset.
+    Transfer,
 }
 
 impl FromStr for TransactionType {
@@ -44,6 +50,7 @@ impl FromStr for TransactionType {
             "interest" => Ok(TransactionType::Interest),
             "buying" => Ok(TransactionType::Buying),
             "selling" => Ok(TransactionType::Selling),
+            "transfer" => Ok(TransactionType::Transfer),
             _ => Err(()),
         }
     }
@@ -59,20 +66,11 @@ impl Display for TransactionType {
 /// E.g. ASTR or BTC or USD (fiat).
 ///
 /// Asset type is always in uppercase.
-#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize)]
+/// Whether it's fiat or a cryptocurrency is decided by the `FiatSettings`.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Deserialize, Serialize)]
+#[serde(from = "String")]
 pub struct AssetType(String);
 impl AssetType {
-    /// Check if the asset is a fiat currency.
-    /// Right now only EUR is supported as fiat.
-    pub fn is_fiat(&self) -> bool {
-        matches!(&self.0[..], "EUR")
-    }
-
-    /// Check if the asset is a cryptocurrency.
-    pub fn is_crypto(&self) -> bool {
-        !self.is_fiat() && !self.0.is_empty()
-    }
-
     /// Consume self, return inner string.
     pub fn inner(self) -> String {
         self.0
@@ -87,6 +85,12 @@ impl FromStr for AssetType {
     }
 }
 
+impl From<String> for AssetType {
+    fn from(input: String) -> Self {
+        AssetType(input.to_uppercase().trim().to_string())
+    }
+}
+
 impl Deref for AssetType {
     type Target = str;
 
@@ -101,13 +105,48 @@ impl Display for AssetType {
     }
 }
 
+/// Key under which the holdings of an asset are tracked.
+///
+/// When holdings are tracked per account, the key contains the account (wallet or exchange) name.
+/// Otherwise all holdings of an asset are tracked together, and the account is `None`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct HoldingKey {
+    /// Account holding the asset, if holdings are tracked per account.
+    pub account: Option<String>,
+    /// Type of the held asset.
+    pub asset: AssetType,
+}
+
+impl HoldingKey {
+    /// Create a new `HoldingKey` instance.
+    ///
+    /// Account is only kept if `per_account` is `true`.
+    pub fn new(asset: AssetType, account: Option<&str>, per_account: bool) -> Self {
+        HoldingKey {
+            account: account.filter(|_| per_account).map(str::to_string),
+            asset,
+        }
+    }
+}
+
+impl Display for HoldingKey {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match &self.account {
+            Some(account) => write!(f, "{} @ {}", self.asset, account),
+            None => write!(f, "{}", self.asset),
+        }
+    }
+}
+
 /// Represents a single transaction that resulted in modification of the ledger.
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Debug, Eq, PartialEq, Clone, Serialize)]
 pub struct Transaction {
     /// Ordinal number of the transaction in the ledger.
     ordinal: u32,
     /// Date on which the transaction was made.
     date: NaiveDate,
+    /// Exact time at which the transaction was made, if known.
+    timestamp: Option<DateTime<Utc>>,
     /// Type of transaction (e.g. Sell or a swap)
     tx_type: TransactionType,
     /// Type of the input token.
@@ -118,12 +157,25 @@ pub struct Transaction {
     output_type: AssetType,
     /// Amount of the output token.
     output_amount: Decimal,
-    /// Transaction context, to help with error messages (e.g. filename, sheet, row).
-    extra_info: String,
+    /// Location of the transaction in the input data, to help with error messages.
+    location: SourceLocation,
+    /// Account (wallet or exchange) on which the transaction was made, if known.
+    account: Option<String>,
+    /// Account receiving the assets of a transfer, if different from the `account`.
+    target_account: Option<String>,
+    /// Original input token and amount, if it was converted into the base currency.
+    original_input: Option<(AssetType, Decimal)>,
+    /// Original output token and amount, if it was converted into the base currency.
+    original_output: Option<(AssetType, Decimal)>,
+    /// Fee token and amount paid for the transaction, if any.
+    fee: Option<(AssetType, Decimal)>,
+    /// Original fee token and amount, if it was converted into the base currency.
+    original_fee: Option<(AssetType, Decimal)>,
 }
 
 impl Transaction {
     /// Create a new `Transaction` instance.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         ordinal: u32,
         date: NaiveDate,
@@ -132,17 +184,24 @@ impl Transaction {
         input_amount: Decimal,
         output_type: AssetType,
         output_amount: Decimal,
-        extra_info: String,
+        location: SourceLocation,
     ) -> Self {
         Transaction {
             ordinal,
             date,
+            timestamp: None,
             tx_type,
             input_type,
             input_amount,
             output_type,
             output_amount,
-            extra_info,
+            location,
+            account: None,
+            target_account: None,
+            original_input: None,
+            original_output: None,
+            fee: None,
+            original_fee: None,
         }
     }
 
@@ -157,11 +216,112 @@ impl Transaction {
         self
     }
 
+    /// Consume this transaction and create a new one with the specified time.
+    pub fn new_with_timestamp(mut self, timestamp: Option<DateTime<Utc>>) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// Consume this transaction and create a new one dated in the specified timezone.
+    /// Date of the transactions without a time is kept as is.
+    pub fn new_in_timezone(mut self, timezone: Tz) -> Self {
+        if let Some(timestamp) = self.timestamp {
+            self.date = timestamp.with_timezone(&timezone).date_naive();
+        }
+        self
+    }
+
+    /// Consume this transaction and create a new one with the specified fee.
+    /// Zero fee is the same as no fee.
+    pub fn new_with_fee(mut self, fee: Option<(AssetType, Decimal)>) -> Self {
+        self.fee = fee.filter(|(_, amount)| !amount.is_zero());
+        self
+    }
+
+    /// Consume this transaction and create a new one with the specified accounts.
+    pub fn new_with_accounts(
+        mut self,
+        account: Option<String>,
+        target_account: Option<String>,
+    ) -> Self {
+        self.account = account;
+        self.target_account = target_account;
+        self
+    }
+
+    /// Consume this transaction and create a new one with all fiat amounts expressed in the base currency.
+    /// Original token & amount of the converted legs are kept for audit purposes.
+    ///
+    /// # Arguments
+    /// * `fiat` - Supported fiat currencies & the base currency.
+    /// * `rate` - Returns the value of one unit of the fiat currency in the base currency, on the given date.
+    pub fn new_in_base_currency<F>(
+        mut self,
+        fiat: &FiatSettings,
+        rate: F,
+    ) -> Result<Self, ParseError>
+    where
+        F: Fn(&AssetType, NaiveDate) -> Option<Decimal>,
+    {
+        let rate = |currency: &AssetType, date: NaiveDate| {
+            rate(currency, date).ok_or_else(|| ParseError::MissingExchangeRate {
+                location: self.location.clone(),
+                currency: currency.clone(),
+                date,
+            })
+        };
+
+        let needs_conversion =
+            |token: &AssetType| fiat.is_fiat(token) && !fiat.is_base_currency(token);
+
+        let input = if needs_conversion(&self.input_type) {
+            Some(self.input_amount * rate(&self.input_type, self.date)?)
+        } else {
+            None
+        };
+        let output = if needs_conversion(&self.output_type) {
+            Some(self.output_amount * rate(&self.output_type, self.date)?)
+        } else {
+            None
+        };
+
+        let fee = match &self.fee {
+            Some((token, amount)) if needs_conversion(token) => {
+                Some(amount * rate(token, self.date)?)
+            }
+            _ => None,
+        };
+
+        let base_currency = &fiat.base_currency;
+        if let Some(converted) = input {
+            let original_type = std::mem::replace(&mut self.input_type, base_currency.clone());
+            self.original_input = Some((original_type, self.input_amount));
+            self.input_amount = converted;
+        }
+        if let Some(converted) = output {
+            let original_type = std::mem::replace(&mut self.output_type, base_currency.clone());
+            self.original_output = Some((original_type, self.output_amount));
+            self.output_amount = converted;
+        }
+        if let (Some(converted), Some((token, amount))) = (fee, self.fee.as_mut()) {
+            let original_type = std::mem::replace(token, base_currency.clone());
+            self.original_fee = Some((original_type, *amount));
+            *amount = converted;
+        }
+
+        Ok(self)
+    }
+
     /// Date on which the transaction was made.
     pub fn date(&self) -> NaiveDate {
         self.date
     }
 
+    /// Exact time at which the transaction was made, if known.
+    pub fn timestamp(&self) -> Option<DateTime<Utc>> {
+        self.timestamp
+    }
+
     /// Type of the transaction.
     pub fn tx_type(&self) -> TransactionType {
         self.tx_type
@@ -177,9 +337,43 @@ impl Transaction {
         (self.output_type.clone(), self.output_amount)
     }
 
-    /// Transaction context, to help with error messages (e.g. filename, sheet, row).
-    pub fn extra_info(&self) -> &str {
-        &self.extra_info
+    /// Original input token and amount, if the input was converted into the base currency.
+    pub fn original_input(&self) -> Option<&(AssetType, Decimal)> {
+        self.original_input.as_ref()
+    }
+
+    /// Original output token and amount, if the output was converted into the base currency.
+    pub fn original_output(&self) -> Option<&(AssetType, Decimal)> {
+        self.original_output.as_ref()
+    }
+
+    /// Fee token and amount paid for the transaction, if any.
+    pub fn fee(&self) -> Option<&(AssetType, Decimal)> {
+        self.fee.as_ref()
+    }
+
+    /// Original fee token and amount, if the fee was converted into the base currency.
+    pub fn original_fee(&self) -> Option<&(AssetType, Decimal)> {
+        self.original_fee.as_ref()
+    }
+
+    /// Location of the transaction in the input data, to help with error messages.
+    pub fn location(&self) -> &SourceLocation {
+        &self.location
+    }
+
+    /// Account (wallet or exchange) on which the transaction was made, if known.
+    pub fn account(&self) -> Option<&str> {
+        self.account.as_deref()
+    }
+
+    /// Account which receives the output of the transaction.
+    /// Only transfers can move assets to a different account, for all other transactions it's the same as `account`.
+    pub fn receiving_account(&self) -> Option<&str> {
+        match self.tx_type {
+            TransactionType::Transfer => self.target_account.as_deref().or(self.account()),
+            _ => self.account(),
+        }
     }
 
     /// Cost basis of the transaction.
@@ -198,8 +392,10 @@ impl Transaction {
     /// Sale price of the transaction.
     /// This is the price at which the output token was sold.
     /// E.g. if 1.5 BTC was sold for 750 USD, the sale price is 500 USD.
-    pub fn sale_price(&self) -> Option<Decimal> {
-        if self.input_amount == Decimal::ZERO || !self.output_type.is_fiat() {
+    ///
+    /// `None` if the output token isn't fiat, according to the provided `FiatSettings`.
+    pub fn sale_price(&self, fiat: &FiatSettings) -> Option<Decimal> {
+        if self.input_amount == Decimal::ZERO || !fiat.is_fiat(&self.output_type) {
             None
         } else {
             Some(self.output_amount / self.input_amount)
@@ -220,6 +416,102 @@ impl Display for Transaction {
             self.output_amount,
             self.output_type,
             self.tx_type
-        )
+        )?;
+
+        for (token, amount) in self.original_input.iter().chain(&self.original_output) {
+            write!(f, " [converted from {amount} {token}]")?;
+        }
+
+        if let Some((token, amount)) = &self.fee {
+            match &self.original_fee {
+                Some((original_token, original_amount)) => write!(
+                    f,
+                    " [fee {amount} {token}, converted from {original_amount} {original_token}]"
+                )?,
+                None => write!(f, " [fee {amount} {token}]")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prices::PriceTable;
+    use crate::test_utils::{asset, date, dec, tx};
+    use crate::types::TransactionType::{Buying, Selling};
+
+    fn fiat() -> FiatSettings {
+        FiatSettings {
+            currencies: vec![asset("EUR"), asset("USD")],
+            ..Default::default()
+        }
+    }
+
+    fn rates() -> PriceTable {
+        let mut rates = PriceTable::default();
+        rates.insert(asset("USD"), date("2024-01-02"), dec("0.9"));
+        rates.insert(asset("USD"), date("2024-01-10"), dec("0.8"));
+        rates
+    }
+
+    #[test]
+    fn fiat_amounts_are_converted_into_the_base_currency() {
+        let rates = rates();
+        let convert = |transaction: Transaction| {
+            transaction
+                .new_in_base_currency(&fiat(), |currency, date| rates.price(currency, date))
+                .unwrap()
+        };
+
+        let buy = convert(tx(1, "2024-01-05", Buying, ("USD", "100"), ("BTC", "0.01")));
+        assert_eq!(buy.input(), (asset("EUR"), dec("90")));
+        assert_eq!(buy.original_input(), Some(&(asset("USD"), dec("100"))));
+        assert_eq!(buy.output(), (asset("BTC"), dec("0.01")));
+        assert_eq!(buy.original_output(), None);
+
+        let sell = convert(tx(
+            2,
+            "2024-01-10",
+            Selling,
+            ("BTC", "0.01"),
+            ("USD", "200"),
+        ));
+        assert_eq!(sell.output(), (asset("EUR"), dec("160")));
+        assert_eq!(sell.original_output(), Some(&(asset("USD"), dec("200"))));
+        assert_eq!(sell.original_input(), None);
+
+        let in_base = tx(3, "2024-01-10", Selling, ("BTC", "0.01"), ("EUR", "150"));
+        assert_eq!(convert(in_base.clone()), in_base);
+    }
+
+    #[test]
+    fn missing_exchange_rate_is_an_error() {
+        let rates = rates();
+        let transaction = tx(1, "2024-01-01", Buying, ("USD", "100"), ("BTC", "0.01"));
+
+        let error = transaction
+            .new_in_base_currency(&fiat(), |currency, date| rates.price(currency, date))
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            ParseError::MissingExchangeRate { currency, date: found, .. }
+                if currency == asset("USD") && found == date("2024-01-01")
+        ));
+    }
+
+    #[test]
+    fn fee_is_converted_into_the_base_currency() {
+        let rates = rates();
+        let transaction = tx(1, "2024-01-05", Buying, ("EUR", "100"), ("BTC", "0.01"))
+            .new_with_fee(Some((asset("USD"), dec("10"))))
+            .new_in_base_currency(&fiat(), |currency, date| rates.price(currency, date))
+            .unwrap();
+
+        assert_eq!(transaction.fee(), Some(&(asset("EUR"), dec("9"))));
+        assert_eq!(transaction.original_fee(), Some(&(asset("USD"), dec("10"))));
+        assert_eq!(transaction.original_input(), None);
     }
 }
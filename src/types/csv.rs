@@ -10,59 +10,262 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::borrow::Cow;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{self, BufWriter, Write},
+};
 
 /// Trait for the data that will be written to the CSV file.
 ///
 /// Provides data for a single line in the CSV file.
 pub trait CsvLineData {
     /// Overall ordinal of the transaction.
-    fn ordinal(&self) -> Cow<str>;
+    fn ordinal(&self) -> Cow<'_, str>;
 
     /// Date of the transaction.
-    fn transaction_date(&self) -> Cow<str>;
+    fn transaction_date(&self) -> Cow<'_, str>;
 
     /// Date of acquisition of the asset.
-    fn acquisition_date(&self) -> Cow<str>;
+    fn acquisition_date(&self) -> Cow<'_, str>;
 
     /// Action taken in the transaction (e.g. swap or sell).
-    fn action(&self) -> Cow<str>;
+    fn action(&self) -> Cow<'_, str>;
 
     /// Type of asset used as input in the transaction.
-    fn input_type(&self) -> Cow<str>;
+    fn input_type(&self) -> Cow<'_, str>;
 
     /// Amount of asset used as input in the transaction.
-    fn input_amount(&self) -> Cow<str>;
+    fn input_amount(&self) -> Cow<'_, str>;
 
     /// Type of asset received as output in the transaction.
-    fn output_type(&self) -> Cow<str>;
+    fn output_type(&self) -> Cow<'_, str>;
 
     /// Amount of asset received as output in the transaction.
-    fn output_amount(&self) -> Cow<str>;
+    fn output_amount(&self) -> Cow<'_, str>;
 
     /// Income amount.
     /// `None` if the transaction doesn't exchange asset for fiat.
-    fn income_amount(&self) -> Option<Cow<str>>;
+    fn income_amount(&self) -> Option<Cow<'_, str>>;
 
     /// Expense amount.
     /// `None` if the transaction doesn't exchange asset for fiat.
-    fn expense_amount(&self) -> Option<Cow<str>>;
+    fn expense_amount(&self) -> Option<Cow<'_, str>>;
 
     /// Profit amount.
     /// `None` if the transaction doesn't exchange asset for fiat.
-    fn profit(&self) -> Option<Cow<str>>;
+    fn profit(&self) -> Option<Cow<'_, str>>;
+
+    /// Fee attributed to the transaction, in the base currency.
+    /// `None` if the transaction has no fee.
+    fn fee(&self) -> Option<Cow<'_, str>> {
+        None
+    }
+
+    /// Holding period class of the disposal (e.g. short-term or long-term).
+    /// `None` if the transaction isn't a disposal, or the classification isn't configured.
+    fn holding_period(&self) -> Option<Cow<'_, str>> {
+        None
+    }
+
+    /// Wash sale adjustment, i.e. the disallowed loss of a sale or the cost basis added to a replacement lot.
+    /// `None` if the transaction isn't affected by the wash sale rule.
+    fn wash_sale_adjustment(&self) -> Option<Cow<'_, str>> {
+        None
+    }
+
+    /// Account (wallet or exchange) on which the transaction was made.
+    /// `None` if the account isn't known.
+    fn account(&self) -> Option<Cow<'_, str>>;
+
+    /// Unique Id of the lot created by the transaction.
+    /// `None` if lots aren't tracked.
+    fn lot_id(&self) -> Option<Cow<'_, str>> {
+        None
+    }
+
+    /// Id of the lot consumed by the transaction.
+    /// `None` if the transaction doesn't consume any lot (e.g. buying with fiat).
+    fn parent_lot(&self) -> Option<Cow<'_, str>> {
+        None
+    }
+}
+
+/// Format of the output CSV files.
+///
+/// Keys are part of the top-level config, prefixed with `csv_`.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+pub struct CsvFormat {
+    /// Separator of the fields, must be a single ASCII character.
+    #[serde(rename = "csv_delimiter")]
+    pub delimiter: char,
+    /// Whether the files start with the UTF-8 byte order mark, so Excel detects the encoding.
+    #[serde(rename = "csv_bom", default)]
+    pub bom: bool,
+    /// Line terminator of the records.
+    #[serde(rename = "csv_line_ending", default)]
+    pub line_ending: LineEnding,
+    /// Separator of the decimal places in the numbers, e.g. `,` for the EU locales.
+    #[serde(
+        rename = "csv_decimal_separator",
+        default = "CsvFormat::default_decimal_separator"
+    )]
+    pub decimal_separator: char,
+}
+
+impl CsvFormat {
+    /// Create a new `CsvFormat` instance, with the given delimiter & the defaults otherwise.
+    pub fn new(delimiter: char) -> Self {
+        CsvFormat {
+            delimiter,
+            bom: false,
+            line_ending: LineEnding::default(),
+            decimal_separator: Self::default_decimal_separator(),
+        }
+    }
+
+    fn default_decimal_separator() -> char {
+        '.'
+    }
+
+    /// Format the field, using the configured decimal separator for the numbers.
+    fn format<'a>(&self, field: &'a CsvField) -> Cow<'a, str> {
+        match field {
+            CsvField::Number(number) if self.decimal_separator != '.' => {
+                Cow::Owned(number.replace('.', &self.decimal_separator.to_string()))
+            }
+            CsvField::Text(value) | CsvField::Number(value) => Cow::Borrowed(value),
+        }
+    }
+}
+
+/// Line terminator of the records in the output CSV files.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    /// Unix line ending, `\n`.
+    #[default]
+    Lf,
+    /// Windows line ending, `\r\n`, as expected by Excel.
+    Crlf,
+}
+
+/// Single field of a line in the output CSV file.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CsvField {
+    /// Plain text, written as is.
+    Text(String),
+    /// Decimal number, written with the configured decimal separator.
+    Number(String),
+}
+
+impl From<String> for CsvField {
+    fn from(value: String) -> Self {
+        CsvField::Text(value)
+    }
+}
+
+impl From<&str> for CsvField {
+    fn from(value: &str) -> Self {
+        CsvField::Text(value.to_string())
+    }
+}
+
+impl From<Decimal> for CsvField {
+    fn from(value: Decimal) -> Self {
+        CsvField::Number(value.to_string())
+    }
+}
+
+impl From<Option<Decimal>> for CsvField {
+    fn from(value: Option<Decimal>) -> Self {
+        value.map_or_else(|| CsvField::Text(String::new()), CsvField::from)
+    }
+}
+
+/// Writer of the output CSV files.
+///
+/// Fields containing the delimiter, a quote or a line break are quoted, and the quotes are escaped (RFC 4180).
+pub struct CsvWriter<W: Write> {
+    writer: ::csv::Writer<W>,
+    format: CsvFormat,
+}
+
+impl CsvWriter<BufWriter<File>> {
+    /// Create a new `CsvWriter` instance, writing to the file at the given path.
+    pub fn from_path(path: &str, format: &CsvFormat) -> Result<Self, ::csv::Error> {
+        Self::new(BufWriter::new(File::create(path)?), format)
+    }
+}
+
+impl<W: Write> CsvWriter<W> {
+    /// Create a new `CsvWriter` instance, writing the BOM right away if it's configured.
+    pub fn new(mut writer: W, format: &CsvFormat) -> Result<Self, ::csv::Error> {
+        let delimiter = u8::try_from(format.delimiter)
+            .ok()
+            .filter(u8::is_ascii)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "CSV delimiter must be a single ASCII character, got: {:?}",
+                        format.delimiter
+                    ),
+                )
+            })?;
+        let terminator = match format.line_ending {
+            LineEnding::Lf => ::csv::Terminator::Any(b'\n'),
+            LineEnding::Crlf => ::csv::Terminator::CRLF,
+        };
+
+        if format.bom {
+            writer.write_all("\u{feff}".as_bytes())?;
+        }
+
+        Ok(CsvWriter {
+            writer: ::csv::WriterBuilder::new()
+                .delimiter(delimiter)
+                .terminator(terminator)
+                .from_writer(writer),
+            format: format.clone(),
+        })
+    }
+
+    /// Write a single record, i.e. a line of the CSV file.
+    pub fn write_record<I>(&mut self, fields: I) -> Result<(), ::csv::Error>
+    where
+        I: IntoIterator,
+        I::Item: Into<CsvField>,
+    {
+        let fields = fields.into_iter().map(Into::into).collect::<Vec<_>>();
+        self.writer.write_record(
+            fields
+                .iter()
+                .map(|field| self.format.format(field).into_owned()),
+        )
+    }
+
+    /// Flush the buffered records & return the underlying writer.
+    pub fn into_inner(self) -> Result<W, ::csv::Error> {
+        self.writer
+            .into_inner()
+            .map_err(|error| ::csv::Error::from(error.into_error()))
+    }
 }
 
 /// Helper for writing data to the CSV file.
 ///
 /// Provides utility functions like converting data to a single line in the CSV file & generating the header.
 pub struct CsvHelper<T: CsvLineData> {
-    delimiter: String,
+    format: CsvFormat,
     _phantom: std::marker::PhantomData<T>,
 }
 
 impl<T: CsvLineData> CsvHelper<T> {
-    const HEADER_ELEMENTS: [&'static str; 11] = [
+    /// Elements of the CSV header, matching the order of the `CsvLineData` fields.
+    pub const HEADER_ELEMENTS: [&'static str; 17] = [
         "Ordinal",
         "Transaction Date",
         "Acquisition Date",
@@ -74,54 +277,201 @@ impl<T: CsvLineData> CsvHelper<T> {
         "Income Amount",
         "Expense Amount",
         "Profit",
+        "Fee",
+        "Holding Period",
+        "Wash Sale Adjustment",
+        "Account",
+        "Lot ID",
+        "Parent Lot",
     ];
 
     /// Create a new `CsvHelper` instance.
-    pub fn new(delimiter: String) -> Self {
+    pub fn new(format: CsvFormat) -> Self {
         Self {
-            delimiter,
+            format,
             _phantom: std::marker::PhantomData,
         }
     }
 
-    /// Delimiter used in the CSV file.
-    fn delimiter(&self) -> &str {
-        &self.delimiter
-    }
-
     /// Discrete elements of the CSV header.
     pub fn csv_header_elements(&self) -> &[&str] {
         &Self::HEADER_ELEMENTS
     }
 
-    /// Full CSV header, as a single string.
-    pub fn csv_header(&self) -> String {
-        self.csv_header_elements().join(self.delimiter())
+    /// Convert the data to a single line in the CSV file, with the numbers already formatted.
+    ///
+    /// Elements aren't quoted, use the `write` to get a valid CSV file.
+    pub fn to_csv_line_elements(&self, data: T) -> Vec<String> {
+        self.to_csv_fields(data)
+            .iter()
+            .map(|field| self.format.format(field).into_owned())
+            .collect()
     }
 
-    /// Convert the data to a single line in the CSV file.
-    pub fn to_csv_line_elements(&self, data: T) -> Vec<String> {
+    /// Convert the data to the fields of a single line in the CSV file.
+    pub fn to_csv_fields(&self, data: T) -> Vec<CsvField> {
+        let text =
+            |value: Option<Cow<'_, str>>| CsvField::Text(value.unwrap_or_default().into_owned());
+        let number = |value: Option<Cow<'_, str>>| match value {
+            Some(value) => CsvField::Number(value.into_owned()),
+            None => CsvField::Text(String::new()),
+        };
+
         vec![
-            data.ordinal().into_owned(),
-            data.transaction_date().into_owned(),
-            data.acquisition_date().into_owned(),
-            data.action().into_owned(),
-            data.input_type().into_owned(),
-            data.input_amount().into_owned(),
-            data.output_type().into_owned(),
-            data.output_amount().into_owned(),
-            data.income_amount()
-                .map(|x| x.into_owned())
-                .unwrap_or_default(),
-            data.expense_amount()
-                .map(|x| x.into_owned())
-                .unwrap_or_default(),
-            data.profit().map(|x| x.into_owned()).unwrap_or_default(),
+            text(Some(data.ordinal())),
+            text(Some(data.transaction_date())),
+            text(Some(data.acquisition_date())),
+            text(Some(data.action())),
+            text(Some(data.input_type())),
+            number(Some(data.input_amount())),
+            text(Some(data.output_type())),
+            number(Some(data.output_amount())),
+            number(data.income_amount()),
+            number(data.expense_amount()),
+            number(data.profit()),
+            number(data.fee()),
+            text(data.holding_period()),
+            number(data.wash_sale_adjustment()),
+            text(data.account()),
+            text(data.lot_id()),
+            text(data.parent_lot()),
         ]
     }
 
-    /// Convert the data to a single line in the CSV file.
-    pub fn to_csv_line(&self, data: T) -> String {
-        self.to_csv_line_elements(data).join(self.delimiter())
+    /// Write the header & all lines to the writer, e.g. a file.
+    pub fn write<W: Write>(
+        &self,
+        writer: W,
+        lines: impl IntoIterator<Item = T>,
+    ) -> Result<W, ::csv::Error> {
+        let mut writer = CsvWriter::new(writer, &self.format)?;
+        writer.write_record(self.csv_header_elements().iter().copied())?;
+        for line in lines {
+            writer.write_record(self.to_csv_fields(line))?;
+        }
+        writer.into_inner()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fifo::{Ledger, LedgerSettings},
+        test_utils::{dec, tx},
+        types::TransactionType::{Buying, Selling},
+    };
+
+    fn write(format: &CsvFormat, records: Vec<Vec<CsvField>>) -> String {
+        let mut writer = CsvWriter::new(Vec::new(), format).unwrap();
+        for record in records {
+            writer.write_record(record).unwrap();
+        }
+        String::from_utf8(writer.into_inner().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn fields_are_quoted_when_needed() {
+        let output = write(
+            &CsvFormat::new(','),
+            vec![vec![
+                "plain".into(),
+                "a,b".into(),
+                "say \"hi\"".into(),
+                "two\nlines".into(),
+                dec("1.5").into(),
+                None.into(),
+            ]],
+        );
+
+        assert_eq!(
+            output,
+            "plain,\"a,b\",\"say \"\"hi\"\"\",\"two\nlines\",1.5,\n"
+        );
+    }
+
+    #[test]
+    fn excel_friendly_format() {
+        let format: CsvFormat = toml::from_str(
+            r#"
+            csv_delimiter = ";"
+            csv_bom = true
+            csv_line_ending = "crlf"
+            csv_decimal_separator = ","
+            "#,
+        )
+        .unwrap();
+        let output = write(
+            &format,
+            vec![
+                vec!["Date".into(), "Amount".into()],
+                vec!["01.02.2024".into(), dec("-1234.5").into()],
+                vec!["a;b".into(), dec("2").into()],
+            ],
+        );
+
+        // Only the numbers use the decimal separator, the dates are kept as is.
+        assert_eq!(
+            output,
+            "\u{feff}Date;Amount\r\n01.02.2024;-1234,5\r\n\"a;b\";2\r\n"
+        );
+    }
+
+    #[test]
+    fn non_ascii_delimiter_is_rejected() {
+        assert!(CsvWriter::new(Vec::new(), &CsvFormat::new('§')).is_err());
+    }
+
+    #[test]
+    fn ledger_breakdown_round_trip() {
+        let transactions = vec![
+            tx(1, "2024-01-05", Buying, ("EUR", "100"), ("BTC", "0.5"))
+                .new_with_accounts(Some("Kraken; \"Pro\"".to_string()), None),
+            tx(2, "2024-02-10", Selling, ("BTC", "0.5"), ("EUR", "80.25"))
+                .new_with_accounts(Some("Kraken; \"Pro\"".to_string()), None),
+        ];
+        let ledger = Ledger::new(transactions, LedgerSettings::default(), None).unwrap();
+
+        let mut format = CsvFormat::new(';');
+        format.decimal_separator = ',';
+        let helper = CsvHelper::new(format);
+        let output = helper.write(Vec::new(), ledger.csv_line_iter()).unwrap();
+
+        let mut reader = ::csv::ReaderBuilder::new()
+            .delimiter(b';')
+            .from_reader(output.as_slice());
+        let header: Vec<_> = reader
+            .headers()
+            .unwrap()
+            .iter()
+            .map(str::to_string)
+            .collect();
+        assert_eq!(header, helper.csv_header_elements());
+
+        let records: Vec<_> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(records.len(), 2);
+        let sale: Vec<_> = records[1].iter().collect();
+        assert_eq!(
+            sale,
+            [
+                "2",
+                "10.02.2024",
+                "05.01.2024",
+                "Selling",
+                "BTC",
+                "0,5",
+                "EUR",
+                "80,25",
+                "80,25",
+                "100,0",
+                "-19,75",
+                "",
+                "",
+                "",
+                "Kraken; \"Pro\"",
+                "2",
+                "1",
+            ]
+        );
     }
 }
@@ -0,0 +1,360 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Errors which can occur while parsing, validating & processing the transactions.
+//!
+//! Each error carries the structured context of the failure (e.g. source location, ordinal, asset, amounts),
+//! so the callers can handle the specific failure kinds, and render them in their own way.
+
+use super::{AssetType, HoldingKey, TransactionType};
+
+use chrono::NaiveDate;
+use itertools::Itertools;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::{
+    fmt::{self, Display, Formatter},
+    sync::Arc,
+};
+use thiserror::Error;
+
+/// Boxed error of an underlying library (e.g. I/O or file format).
+pub type BoxedError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Location of the transaction in the input data.
+///
+/// Each transaction keeps its location, hence the names are shared to keep the cloning cheap.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize)]
+pub struct SourceLocation {
+    /// Name of the file.
+    pub file: Arc<str>,
+    /// Name of the sheet, if the file has any.
+    pub sheet: Option<Arc<str>>,
+    /// Row number (1-based), if the location refers to a single row.
+    pub row: Option<usize>,
+}
+
+impl SourceLocation {
+    /// Create a new `SourceLocation` for the whole file.
+    pub fn new(file: &str) -> Self {
+        SourceLocation {
+            file: file.into(),
+            sheet: None,
+            row: None,
+        }
+    }
+
+    /// Consume this location and create a new one within the specified sheet.
+    pub fn with_sheet(mut self, sheet: &str) -> Self {
+        self.sheet = Some(sheet.into());
+        self
+    }
+
+    /// Consume this location and create a new one pointing to the specified row.
+    pub fn with_row(mut self, row: usize) -> Self {
+        self.row = Some(row);
+        self
+    }
+}
+
+impl Display for SourceLocation {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "File: '{}'", self.file)?;
+        if let Some(sheet) = &self.sheet {
+            write!(f, ", Sheet: '{sheet}'")?;
+        }
+        if let Some(row) = self.row {
+            write!(f, ", Row: {row}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Error which occurred while parsing the input data.
+#[derive(Debug, Error)]
+pub enum ParseError {
+    /// File couldn't be opened or read.
+    #[error("Cannot read file '{file}': {source}")]
+    Read {
+        file: String,
+        #[source]
+        source: BoxedError,
+    },
+    /// Sheet doesn't exist in the file.
+    #[error("File: '{file}'; Sheet '{sheet}' not found")]
+    SheetNotFound { file: String, sheet: String },
+    /// Delimiter of the file isn't a single-byte character.
+    #[error("File: '{file}'; Delimiter '{delimiter}' must be an ASCII character")]
+    InvalidDelimiter { file: String, delimiter: char },
+    /// Header row doesn't exist.
+    #[error("{location}; Header row doesn't exist")]
+    MissingHeaderRow { location: SourceLocation },
+    /// Columns couldn't be resolved.
+    #[error("{location}; {source}")]
+    Columns {
+        location: SourceLocation,
+        #[source]
+        source: ColumnError,
+    },
+    /// Cell holding one of the transaction fields is invalid.
+    #[error("{location}; Invalid {field} - please check! Error: {source}")]
+    InvalidCell {
+        location: SourceLocation,
+        /// Name of the transaction field, e.g. `input amount`.
+        field: &'static str,
+        #[source]
+        source: CellError,
+    },
+    /// Transaction date is before the date of the previous transaction.
+    #[error("{location}; Date {date} is before the previous date {previous} - dates must be monotonically increasing, please check!")]
+    DateOrder {
+        location: SourceLocation,
+        date: NaiveDate,
+        previous: NaiveDate,
+    },
+    /// Data was found after the first empty row, it would be skipped.
+    #[error("{location}; Non-empty cells found after the first empty row - please check!")]
+    DataAfterEnd { location: SourceLocation },
+    /// Exchange rate required to convert the fiat amount into the base currency is missing.
+    #[error("{location}; No exchange rate available for {currency} on or before {date}")]
+    MissingExchangeRate {
+        location: SourceLocation,
+        currency: AssetType,
+        date: NaiveDate,
+    },
+}
+
+/// Error of the column mapping.
+#[derive(Debug, Clone, Eq, PartialEq, Error)]
+pub enum ColumnError {
+    /// Mapped headers are missing from the header row.
+    #[error("Missing required header(s): {}. Found headers: {found:?}", missing.join(", "))]
+    MissingHeaders {
+        missing: Vec<String>,
+        found: Vec<String>,
+    },
+    /// Column letters are invalid.
+    #[error("Invalid column(s) {columns:?}, expected column letter(s) like 'A' or 'AB'")]
+    InvalidColumns { columns: Vec<String> },
+    /// Only one of the fee token & fee amount columns is mapped.
+    #[error("Fee token and fee amount columns must be mapped together")]
+    IncompleteFee,
+}
+
+/// Error of a single cell (or CSV value).
+#[derive(Debug, Clone, Eq, PartialEq, Error)]
+pub enum CellError {
+    /// Value is missing, e.g. the row is too short.
+    #[error("Value in column {column} is missing")]
+    Missing { column: String },
+    /// Cell has an unexpected type, e.g. a number instead of a date.
+    #[error("Expected {expected}, found: {found}")]
+    UnexpectedType {
+        expected: &'static str,
+        found: String,
+    },
+    /// Date can't be parsed using any of the formats.
+    #[error("Cannot parse date '{value}' using any of the formats: {formats:?}")]
+    InvalidDate { value: String, formats: Vec<String> },
+    /// Local time doesn't exist in the timezone, e.g. when the clocks go forward.
+    #[error("Time '{value}' doesn't exist in the timezone '{timezone}'")]
+    NonexistentTime { value: String, timezone: String },
+    /// Number can't be parsed.
+    #[error("Cannot parse number '{value}': {reason}")]
+    InvalidNumber { value: String, reason: String },
+    /// Decimal & thousands separators are the same.
+    #[error("Decimal separator '{separator}' and thousands separator must differ")]
+    ConflictingSeparators { separator: char },
+    /// Ordinal isn't a non-negative integer.
+    #[error("Ordinal must be an integer, found '{value}'")]
+    InvalidOrdinal { value: String },
+    /// Transaction type is unknown.
+    #[error("Unknown transaction type '{value}'")]
+    InvalidTransactionType { value: String },
+}
+
+/// Error found during the contextual validation of the transactions.
+#[derive(Debug, Clone, Eq, PartialEq, Error)]
+#[error("Context: {location}; Transaction {ordinal}: {kind}")]
+pub struct ValidationError {
+    /// Ordinal number of the invalid transaction.
+    pub ordinal: u32,
+    /// Location of the invalid transaction in the input data.
+    pub location: SourceLocation,
+    /// Kind of the validation failure.
+    pub kind: ValidationErrorKind,
+}
+
+/// All validation failures found in the transactions.
+///
+/// When displayed, the errors are grouped by the file & sheet in which the invalid transactions are defined.
+#[derive(Debug, Clone, Eq, PartialEq, Error)]
+pub struct ValidationErrors(pub Vec<ValidationError>);
+
+impl Display for ValidationErrors {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Found {} validation error(s):", self.0.len())?;
+
+        let groups = self
+            .0
+            .iter()
+            .sorted_by_key(|error| (&error.location.file, &error.location.sheet))
+            .chunk_by(|error| (&error.location.file, &error.location.sheet));
+
+        for ((file, sheet), errors) in &groups {
+            write!(f, "\nFile: '{file}'")?;
+            if let Some(sheet) = sheet {
+                write!(f, ", Sheet: '{sheet}'")?;
+            }
+            write!(f, ":")?;
+
+            for error in errors {
+                match error.location.row {
+                    Some(row) => write!(f, "\n  Row {row}, ")?,
+                    None => write!(f, "\n  ")?,
+                }
+                write!(f, "Transaction {}: {}", error.ordinal, error.kind)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Kind of the validation failure.
+#[derive(Debug, Clone, Eq, PartialEq, Error)]
+pub enum ValidationErrorKind {
+    /// Ordinal numbers aren't sequential.
+    #[error("Ordinal number mismatch: expected {expected}, found {found}")]
+    OrdinalMismatch { expected: u32, found: u32 },
+    /// Transaction date is before the date of the previous transaction.
+    #[error("Date mismatch: expected >= {previous}, found {found}")]
+    DateOrder {
+        previous: NaiveDate,
+        found: NaiveDate,
+    },
+    /// Holding doesn't have enough balance to cover the input amount.
+    #[error("Negative balance for {holding}: available {available}, required {required}")]
+    NegativeBalance {
+        holding: HoldingKey,
+        available: Decimal,
+        required: Decimal,
+    },
+    /// Input asset was never acquired.
+    #[error("Holding {holding} not found, required {required}")]
+    UnknownHolding {
+        holding: HoldingKey,
+        required: Decimal,
+    },
+    /// Balance of the holding overflowed.
+    #[error("Balance overflow for {holding} when adding {amount}")]
+    Overflow {
+        holding: HoldingKey,
+        amount: Decimal,
+    },
+    /// Input amount is zero.
+    #[error("{tx_type} transaction should have non-zero input amount")]
+    ZeroInputAmount { tx_type: TransactionType },
+    /// Output amount is zero.
+    #[error("{tx_type} transaction should have non-zero output amount")]
+    ZeroOutputAmount { tx_type: TransactionType },
+    /// Input asset must be fiat.
+    #[error("{tx_type} transaction should have fiat input, found {found}")]
+    InputNotFiat {
+        tx_type: TransactionType,
+        found: AssetType,
+    },
+    /// Input asset must not be fiat.
+    #[error("{tx_type} transaction should not have fiat input, found {found}")]
+    InputIsFiat {
+        tx_type: TransactionType,
+        found: AssetType,
+    },
+    /// Input asset must be crypto.
+    #[error("{tx_type} transaction should have crypto input, found '{found}'")]
+    InputNotCrypto {
+        tx_type: TransactionType,
+        found: AssetType,
+    },
+    /// Output asset must be fiat.
+    #[error("{tx_type} transaction should have fiat output, found {found}")]
+    OutputNotFiat {
+        tx_type: TransactionType,
+        found: AssetType,
+    },
+    /// Output asset must not be fiat.
+    #[error("{tx_type} transaction should not have fiat output, found {found}")]
+    OutputIsFiat {
+        tx_type: TransactionType,
+        found: AssetType,
+    },
+    /// Input & output assets must differ.
+    #[error("{tx_type} transaction should have different input and output tokens, found {token}")]
+    SameTokens {
+        tx_type: TransactionType,
+        token: AssetType,
+    },
+    /// Input & output assets must be the same.
+    #[error("{tx_type} transaction should have the same input and output tokens, found {input} and {output}")]
+    DifferentTokens {
+        tx_type: TransactionType,
+        input: AssetType,
+        output: AssetType,
+    },
+    /// Output amount must not exceed the input amount.
+    #[error("{tx_type} transaction should not have output amount {output} greater than the input amount {input}")]
+    OutputExceedsInput {
+        tx_type: TransactionType,
+        input: Decimal,
+        output: Decimal,
+    },
+    /// Fee amount must not be negative.
+    #[error("Fee amount should not be negative, found {amount} {token}")]
+    NegativeFee { token: AssetType, amount: Decimal },
+}
+
+/// Error which occurred while processing the transactions in the ledger.
+#[derive(Debug, Clone, Eq, PartialEq, Error)]
+#[error("Context: {location}; Transaction {ordinal}: {kind}")]
+pub struct LedgerError {
+    /// Ordinal number of the transaction which couldn't be processed.
+    pub ordinal: u32,
+    /// Location of the transaction in the input data.
+    pub location: SourceLocation,
+    /// Kind of the processing failure.
+    pub kind: LedgerErrorKind,
+}
+
+/// Kind of the ledger processing failure.
+#[derive(Debug, Clone, Eq, PartialEq, Error)]
+pub enum LedgerErrorKind {
+    /// There's no inventory for the consumed holding.
+    #[error("No inventory found for {holding}")]
+    UnknownHolding { holding: HoldingKey },
+    /// Open lots don't cover the consumed amount.
+    #[error("Open lots of {holding} are short by {missing}")]
+    InsufficientLots {
+        holding: HoldingKey,
+        missing: Decimal,
+    },
+    /// Market price required to value the swap is missing, for both of its assets.
+    #[error("No price available for either {output} or {input} on or before {date}")]
+    MissingPrice {
+        input: AssetType,
+        output: AssetType,
+        date: NaiveDate,
+    },
+    /// Fee can't be added to the cost basis, since the transaction acquires no assets.
+    #[error("Fee of {fee} can't be attributed, since no assets are acquired")]
+    UnattributedFee { fee: Decimal },
+}
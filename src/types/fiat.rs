@@ -0,0 +1,112 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::Deserialize;
+
+use super::AssetType;
+
+/// Configuration of the supported fiat currencies.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+pub struct FiatSettings {
+    /// List of assets which are treated as fiat currencies.
+    #[serde(default = "FiatSettings::default_currencies")]
+    pub currencies: Vec<AssetType>,
+    /// Currency in which all reports are made.
+    /// Fiat amounts in other currencies are converted into it.
+    #[serde(default = "FiatSettings::default_base_currency")]
+    pub base_currency: AssetType,
+    /// Path to the file with daily exchange rates, used to convert other fiat currencies into the base currency.
+    pub exchange_rates: Option<String>,
+}
+
+impl FiatSettings {
+    fn default_currencies() -> Vec<AssetType> {
+        vec![Self::default_base_currency()]
+    }
+
+    fn default_base_currency() -> AssetType {
+        AssetType::from("EUR".to_string())
+    }
+
+    /// Check that the settings are consistent, i.e. that the base currency is one of the fiat currencies.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.currencies.contains(&self.base_currency) {
+            Ok(())
+        } else {
+            Err(format!(
+                "Base currency {} must be one of the fiat currencies: {:?}",
+                self.base_currency, self.currencies
+            ))
+        }
+    }
+
+    /// Check if the asset is one of the fiat currencies.
+    pub fn is_fiat(&self, asset: &AssetType) -> bool {
+        self.currencies.contains(asset) || self.is_base_currency(asset)
+    }
+
+    /// Check if the asset is the base (reporting) fiat currency.
+    pub fn is_base_currency(&self, asset: &AssetType) -> bool {
+        self.base_currency == *asset
+    }
+
+    /// Check if the asset is a cryptocurrency, i.e. any non-empty asset which isn't fiat.
+    pub fn is_crypto(&self, asset: &AssetType) -> bool {
+        !self.is_fiat(asset) && !asset.is_empty()
+    }
+}
+
+impl Default for FiatSettings {
+    fn default() -> Self {
+        FiatSettings {
+            currencies: Self::default_currencies(),
+            base_currency: Self::default_base_currency(),
+            exchange_rates: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::asset;
+
+    fn settings(currencies: &[&str], base_currency: &str) -> FiatSettings {
+        FiatSettings {
+            currencies: currencies.iter().map(|name| asset(name)).collect(),
+            base_currency: asset(base_currency),
+            exchange_rates: None,
+        }
+    }
+
+    #[test]
+    fn base_currency_must_be_one_of_the_currencies() {
+        assert!(FiatSettings::default().validate().is_ok());
+        assert!(settings(&["EUR", "USD"], "USD").validate().is_ok());
+
+        let error = settings(&["EUR", "USD"], "GBP").validate().unwrap_err();
+        assert!(error.contains("GBP"), "{error}");
+    }
+
+    #[test]
+    fn assets_are_classified_as_fiat_or_crypto() {
+        let fiat = settings(&["EUR", "USD"], "EUR");
+
+        assert!(fiat.is_fiat(&asset("USD")));
+        assert!(fiat.is_base_currency(&asset("EUR")));
+        assert!(!fiat.is_base_currency(&asset("USD")));
+
+        assert!(fiat.is_crypto(&asset("BTC")));
+        assert!(!fiat.is_crypto(&asset("USD")));
+        assert!(!fiat.is_crypto(&asset("")));
+    }
+}
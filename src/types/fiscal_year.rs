@@ -0,0 +1,133 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{Datelike, NaiveDate};
+use serde::Deserialize;
+use std::fmt::{self, Display, Formatter};
+
+/// Fiscal year, determined by the month & day on which it starts.
+///
+/// Fiscal years are identified by the calendar year in which they start.
+/// Calendar years are labeled as such (e.g. `2024`), others by both years (e.g. `2023/24`).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub struct FiscalYear {
+    /// Month in which the fiscal year starts (1-12).
+    month: u32,
+    /// Day of the month on which the fiscal year starts.
+    day: u32,
+}
+
+impl FiscalYear {
+    /// Calendar year, starting on 1 January.
+    pub const CALENDAR: FiscalYear = FiscalYear { month: 1, day: 1 };
+
+    /// UK tax year, starting on 6 April.
+    pub const UK_TAX_YEAR: FiscalYear = FiscalYear { month: 4, day: 6 };
+
+    /// Create a new `FiscalYear` instance, starting on the given month & day.
+    ///
+    /// Returns `None` if the day doesn't exist in every year, e.g. 29 February.
+    pub fn new(month: u32, day: u32) -> Option<Self> {
+        // Non-leap year, so that the fiscal year starts on the same day every year.
+        NaiveDate::from_ymd_opt(2001, month, day).map(|_| FiscalYear { month, day })
+    }
+
+    /// Calendar year in which the fiscal year of the date starts.
+    pub fn start_year(&self, date: NaiveDate) -> i32 {
+        if (date.month(), date.day()) >= (self.month, self.day) {
+            date.year()
+        } else {
+            date.year() - 1
+        }
+    }
+
+    /// Label of the fiscal year starting in the given calendar year, e.g. `2024` or `2023/24`.
+    pub fn label(&self, start_year: i32) -> String {
+        if *self == Self::CALENDAR {
+            start_year.to_string()
+        } else {
+            format!("{}/{:02}", start_year, (start_year + 1).rem_euclid(100))
+        }
+    }
+}
+
+impl Default for FiscalYear {
+    fn default() -> Self {
+        Self::CALENDAR
+    }
+}
+
+impl TryFrom<String> for FiscalYear {
+    type Error = String;
+
+    /// Parse the fiscal year start, in the `MM-DD` format.
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value
+            .split_once('-')
+            .and_then(|(month, day)| {
+                FiscalYear::new(month.trim().parse().ok()?, day.trim().parse().ok()?)
+            })
+            .ok_or_else(|| {
+                format!("Invalid fiscal year start '{value}', expected a date in the MM-DD format, e.g. 07-01.")
+            })
+    }
+}
+
+impl Display for FiscalYear {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{:02}-{:02}", self.month, self.day)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::date;
+
+    #[test]
+    fn dates_are_bucketed_by_the_start_of_the_fiscal_year() {
+        let uk = FiscalYear::UK_TAX_YEAR;
+        assert_eq!(uk.start_year(date("2024-04-05")), 2023);
+        assert_eq!(uk.start_year(date("2024-04-06")), 2024);
+        assert_eq!(uk.start_year(date("2025-01-01")), 2024);
+
+        let calendar = FiscalYear::CALENDAR;
+        assert_eq!(calendar.start_year(date("2024-01-01")), 2024);
+        assert_eq!(calendar.start_year(date("2024-12-31")), 2024);
+    }
+
+    #[test]
+    fn only_calendar_years_are_labeled_by_a_single_year() {
+        assert_eq!(FiscalYear::CALENDAR.label(2024), "2024");
+        assert_eq!(FiscalYear::UK_TAX_YEAR.label(2023), "2023/24");
+        assert_eq!(FiscalYear::UK_TAX_YEAR.label(1999), "1999/00");
+    }
+
+    #[test]
+    fn start_is_parsed_from_month_and_day() {
+        assert_eq!(
+            FiscalYear::try_from("07-01".to_string()),
+            Ok(FiscalYear::new(7, 1).unwrap())
+        );
+        assert_eq!(
+            FiscalYear::try_from("04-06".to_string()),
+            Ok(FiscalYear::UK_TAX_YEAR)
+        );
+        assert_eq!(FiscalYear::UK_TAX_YEAR.to_string(), "04-06");
+
+        for invalid in ["02-29", "13-01", "0701", "July"] {
+            let error = FiscalYear::try_from(invalid.to_string()).unwrap_err();
+            assert!(error.contains(invalid), "{error}");
+        }
+    }
+}
@@ -12,8 +12,17 @@
 
 mod core;
 mod csv;
+mod error;
+mod fiat;
+mod fiscal_year;
 mod parser;
 
-pub use core::{AssetType, Transaction, TransactionType};
-pub use csv::{CsvHelper, CsvLineData};
-pub use parser::{ParserDataType, TransactionsProvider};
+pub use core::{AssetType, HoldingKey, Transaction, TransactionType};
+pub use csv::{CsvField, CsvFormat, CsvHelper, CsvLineData, CsvWriter, LineEnding};
+pub use error::{
+    CellError, ColumnError, LedgerError, LedgerErrorKind, ParseError, SourceLocation,
+    ValidationError, ValidationErrorKind, ValidationErrors,
+};
+pub use fiat::FiatSettings;
+pub use fiscal_year::FiscalYear;
+pub use parser::{DataParser, ParserDataType, TransactionsProvider};
@@ -10,9 +10,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::types::Transaction;
+use chrono_tz::Tz;
+use itertools::Itertools;
+use std::collections::{BTreeMap, VecDeque};
 
-pub type ParserDataType = Result<Vec<Transaction>, Box<dyn std::error::Error>>;
+use crate::{
+    prices::PriceTable,
+    types::{FiatSettings, ParseError, Transaction},
+};
+
+pub type ParserDataType = Result<Vec<Transaction>, ParseError>;
 
 /// 'Parse' the underlying data source and return a list of transactions.
 ///
@@ -28,23 +35,85 @@ impl<T: Iterator<Item = ParserDataType>> DataParser for T {}
 /// This type will acquire all of the transactions that need to be processed, and prepare them for further validation & processing.
 pub struct TransactionsProvider<T: DataParser> {
     iter: T,
+    /// Supported fiat currencies & the base currency, into which all fiat amounts are converted.
+    fiat: FiatSettings,
+    /// Exchange rates used to convert fiat amounts into the base currency.
+    exchange_rates: Option<PriceTable>,
+    /// Timezone in which the dates of the transactions with a known time are reported.
+    tax_timezone: Option<Tz>,
 }
 
 impl<T: DataParser> TransactionsProvider<T> {
+    /// Create a new `TransactionsProvider` instance.
+    ///
+    /// # Arguments
+    /// * `iter` - Parser of the underlying data source.
+    /// * `fiat` - Supported fiat currencies & the base currency.
+    pub fn new(iter: T, fiat: FiatSettings) -> Self {
+        TransactionsProvider {
+            iter,
+            fiat,
+            exchange_rates: None,
+            tax_timezone: None,
+        }
+    }
+
+    /// Use the provided exchange rates to convert all fiat amounts into the base currency.
+    pub fn with_exchange_rates(mut self, exchange_rates: PriceTable) -> Self {
+        self.exchange_rates = Some(exchange_rates);
+        self
+    }
+
+    /// Report the transactions with a known time on their date in the provided timezone.
+    pub fn with_tax_timezone(mut self, tax_timezone: Tz) -> Self {
+        self.tax_timezone = Some(tax_timezone);
+        self
+    }
+
     /// Consumes the `TransactionsProvider` and returns a list of all transactions, sorted by date.
-    pub fn get(self) -> Result<Vec<Transaction>, Box<dyn std::error::Error>> {
+    ///
+    /// Transactions of each source keep their order within the day.
+    /// Same-day transactions of different sources are ordered by their time, if both of them have one,
+    /// and by the order of the sources otherwise.
+    pub fn get(self) -> Result<Vec<Transaction>, ParseError> {
         let mut transactions = Vec::new();
 
-        for entry in self.iter {
-            transactions.append(&mut entry?);
+        for (source, entry) in self.iter.enumerate() {
+            transactions.extend(entry?.into_iter().map(|tx| (source, tx)));
         }
 
+        // Date the transactions in the tax timezone, before the exchange rates are looked up.
+        if let Some(tax_timezone) = self.tax_timezone {
+            transactions = transactions
+                .into_iter()
+                .map(|(source, tx)| (source, tx.new_in_timezone(tax_timezone)))
+                .collect();
+        }
+
+        // Express all fiat amounts in the base currency.
+        let exchange_rates = self.exchange_rates.unwrap_or_default();
+        transactions = transactions
+            .into_iter()
+            .map(|(source, tx)| {
+                tx.new_in_base_currency(&self.fiat, |currency, date| {
+                    exchange_rates.price(currency, date)
+                })
+                .map(|tx| (source, tx))
+            })
+            .collect::<Result<_, _>>()?;
+
         // In case the files & sheets weren't provided in the correct order.
-        transactions.sort_by_key(|t| t.date());
+        // Stable sort, so the transactions of each source keep their order.
+        transactions.sort_by_key(|(_, tx)| tx.date());
+
+        let mut sorted = Vec::with_capacity(transactions.len());
+        for (_, day) in &transactions.into_iter().chunk_by(|(_, tx)| tx.date()) {
+            sorted.extend(merge_same_day(day));
+        }
 
         // Update the ordinals.
         let mut counter: u32 = 0;
-        transactions = transactions
+        let transactions: Vec<_> = sorted
             .into_iter()
             .map(|tx| {
                 counter += 1;
@@ -58,9 +127,139 @@ impl<T: DataParser> TransactionsProvider<T> {
     }
 }
 
-/// Convenience implementation.
-impl<T: DataParser> From<T> for TransactionsProvider<T> {
-    fn from(iter: T) -> Self {
-        TransactionsProvider { iter }
+/// Merge the transactions of the same day, each tagged with the index of its source.
+///
+/// Transactions of each source keep their order. Of the next transactions of all sources,
+/// the one with the earliest time is taken, while a transaction without a time is never moved
+/// ahead of the transactions of the preceding sources.
+fn merge_same_day(day: impl Iterator<Item = (usize, Transaction)>) -> Vec<Transaction> {
+    let mut sources = BTreeMap::<usize, VecDeque<Transaction>>::new();
+    for (source, tx) in day {
+        sources.entry(source).or_default().push_back(tx);
+    }
+    let mut sources: Vec<_> = sources.into_values().collect();
+
+    let mut merged = Vec::new();
+    loop {
+        let mut next: Option<&mut VecDeque<Transaction>> = None;
+        for source in sources.iter_mut().filter(|source| !source.is_empty()) {
+            let is_earlier = match &next {
+                None => true,
+                Some(current) => matches!(
+                    (source[0].timestamp(), current[0].timestamp()),
+                    (Some(time), Some(current_time)) if time < current_time
+                ),
+            };
+            if is_earlier {
+                next = Some(source);
+            }
+        }
+
+        match next.and_then(VecDeque::pop_front) {
+            Some(tx) => merged.push(tx),
+            None => return merged,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{date, tx};
+    use crate::types::TransactionType::{Buying, Selling};
+
+    use chrono::{DateTime, NaiveDate};
+
+    fn at(transaction: Transaction, time: &str) -> Transaction {
+        let timestamp = DateTime::parse_from_rfc3339(time).unwrap().to_utc();
+        transaction.new_with_timestamp(Some(timestamp))
+    }
+
+    fn buy(row: u32, date: &str) -> Transaction {
+        tx(row, date, Buying, ("EUR", "100"), ("BTC", "1"))
+    }
+
+    /// Rows of the sorted transactions, and their dates.
+    fn order(transactions: &[Transaction]) -> Vec<(u32, usize, NaiveDate)> {
+        transactions
+            .iter()
+            .map(|tx| (tx.ordinal(), tx.location().row.unwrap(), tx.date()))
+            .collect()
+    }
+
+    fn provide(sources: Vec<Vec<Transaction>>, tax_timezone: Option<Tz>) -> Vec<Transaction> {
+        let provider =
+            TransactionsProvider::new(sources.into_iter().map(Ok), FiatSettings::default());
+        match tax_timezone {
+            Some(tax_timezone) => provider.with_tax_timezone(tax_timezone),
+            None => provider,
+        }
+        .get()
+        .unwrap()
+    }
+
+    #[test]
+    fn sources_are_merged_by_date_and_time() {
+        let first = vec![
+            buy(1, "2024-01-01"),
+            at(buy(2, "2024-01-02"), "2024-01-02T15:00:00Z"),
+            buy(3, "2024-01-02"),
+        ];
+        let second = vec![
+            at(buy(10, "2024-01-02"), "2024-01-02T09:00:00Z"),
+            buy(11, "2024-01-02"),
+            buy(12, "2024-01-03"),
+        ];
+        let transactions = provide(vec![first, second], None);
+
+        // Timed transactions of different sources are ordered by their time,
+        // while the ones without a time stay behind the preceding transactions of their source.
+        // Ordinals are reassigned in the final order.
+        assert_eq!(
+            order(&transactions),
+            vec![
+                (1, 2, date("2024-01-01")),
+                (2, 11, date("2024-01-02")),
+                (3, 3, date("2024-01-02")),
+                (4, 4, date("2024-01-02")),
+                (5, 12, date("2024-01-02")),
+                (6, 13, date("2024-01-03")),
+            ]
+        );
+    }
+
+    #[test]
+    fn untimed_transaction_keeps_its_source_order() {
+        let first = vec![
+            at(buy(1, "2024-01-02"), "2024-01-02T10:00:00Z"),
+            tx(2, "2024-01-02", Selling, ("BTC", "1"), ("EUR", "120")),
+        ];
+        let second = vec![at(buy(10, "2024-01-02"), "2024-01-02T08:00:00Z")];
+        let transactions = provide(vec![first, second], None);
+
+        // Sale without a time can't be moved ahead of the purchase it follows.
+        assert_eq!(
+            order(&transactions),
+            vec![
+                (1, 11, date("2024-01-02")),
+                (2, 2, date("2024-01-02")),
+                (3, 3, date("2024-01-02")),
+            ]
+        );
+    }
+
+    #[test]
+    fn tax_timezone_moves_the_dates_of_timed_transactions() {
+        let transactions = vec![
+            // Already the 2nd of January in Tokyo.
+            at(buy(1, "2024-01-01"), "2024-01-01T16:00:00Z"),
+            buy(2, "2024-01-01"),
+        ];
+        let transactions = provide(vec![transactions], Some(Tz::Asia__Tokyo));
+
+        assert_eq!(
+            order(&transactions),
+            vec![(1, 3, date("2024-01-01")), (2, 2, date("2024-01-02"))]
+        );
     }
 }
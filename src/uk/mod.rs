@@ -0,0 +1,781 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! UK share pooling rules, used by HMRC instead of FIFO for crypto assets.
+//!
+//! # Algorithm
+//!
+//! All acquisitions of an asset made on the same day are treated as a single acquisition,
+//! and all its disposals made on the same day as a single disposal.
+//! Disposals are then matched against the acquisitions in the following order:
+//!
+//! 1. **Same-day rule** - acquisitions made on the same day as the disposal.
+//! 2. **Bed & breakfast rule** - acquisitions made within the 30 days after the disposal, earliest first.
+//!    Earlier disposals are matched first.
+//! 3. **Section 104 pool** - all remaining acquisitions, pooled at their average cost.
+//!
+//! Same-day matching is done for all days first, since it takes precedence over the bed & breakfast rule.
+//! Acquisitions which aren't matched by the first two rules are added to the pool of the asset.
+//!
+//! Pools are kept per asset, regardless of the account holding it.
+//! Swaps are disposals of the input asset & acquisitions of the output asset, both at the market value.
+//! Difference between the sent & received amount of a transfer is a fee, disposed of at zero proceeds.
+//! Explicit fees paid in crypto are disposed of at zero proceeds as well.
+//! Unlike in the FIFO ledger, their cost isn't added to the transaction, since it's only known once they are matched.
+//! Explicit fiat fees are allowable costs, deducted from the proceeds of a sale or added to the cost of an acquisition.
+//! Fiat fees of the transfers aren't allowable.
+
+mod tax_year;
+
+pub use tax_year::UkTaxYearReport;
+
+use crate::{
+    prices::PriceTable,
+    types::{
+        AssetType, CsvField, FiatSettings, FiscalYear, HoldingKey, LedgerError, LedgerErrorKind,
+        Transaction, TransactionType,
+    },
+};
+
+use chrono::{Days, NaiveDate};
+use itertools::Itertools;
+use rust_decimal::Decimal;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+};
+
+/// Number of days after the disposal, in which the acquisitions are matched by the bed & breakfast rule.
+const BED_AND_BREAKFAST_DAYS: u64 = 30;
+
+/// Rule by which the disposal was matched with the acquisition.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum MatchingRule {
+    /// Acquisition made on the same day.
+    SameDay,
+    /// Acquisition made within the 30 days after the disposal.
+    BedAndBreakfast,
+    /// Section 104 pool of the asset.
+    Section104,
+}
+
+impl Display for MatchingRule {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            MatchingRule::SameDay => write!(f, "Same-Day"),
+            MatchingRule::BedAndBreakfast => write!(f, "Bed & Breakfast"),
+            MatchingRule::Section104 => write!(f, "Section 104"),
+        }
+    }
+}
+
+/// Part of a disposal, matched by a single rule.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UkDisposal {
+    /// Ordinal numbers of the disposing transactions.
+    /// All disposals of the asset made on the same day are a single disposal.
+    pub ordinals: Vec<u32>,
+    /// Date of the disposal.
+    pub date: NaiveDate,
+    /// Disposed asset.
+    pub asset: AssetType,
+    /// Disposed amount, matched by the rule.
+    pub amount: Decimal,
+    /// Rule by which the amount was matched.
+    pub rule: MatchingRule,
+    /// Date of the matched acquisition, `None` for the Section 104 pool.
+    pub acquisition_date: Option<NaiveDate>,
+    /// Disposal proceeds of the amount, in the base currency.
+    pub proceeds: Decimal,
+    /// Allowable cost of the amount, in the base currency.
+    pub allowable_cost: Decimal,
+}
+
+impl UkDisposal {
+    /// Elements of the CSV header, matching the `csv_line_elements`.
+    pub const CSV_HEADER_ELEMENTS: [&'static str; 10] = [
+        "Ordinal",
+        "Disposal Date",
+        "Tax Year",
+        "Asset",
+        "Amount",
+        "Matching Rule",
+        "Acquisition Date",
+        "Proceeds",
+        "Allowable Cost",
+        "Gain",
+    ];
+
+    /// Gain (or loss, if negative) of the disposed amount.
+    pub fn gain(&self) -> Decimal {
+        self.proceeds - self.allowable_cost
+    }
+
+    /// Ordinal numbers of the disposing transactions, separated by a comma.
+    pub fn ordinals_label(&self) -> String {
+        self.ordinals.iter().map(u32::to_string).join(", ")
+    }
+
+    /// Elements of the single line in the CSV file.
+    pub fn csv_line_elements(&self) -> Vec<CsvField> {
+        vec![
+            self.ordinals_label().into(),
+            self.date.format("%d.%m.%Y").to_string().into(),
+            FiscalYear::UK_TAX_YEAR
+                .label(FiscalYear::UK_TAX_YEAR.start_year(self.date))
+                .into(),
+            self.asset.to_string().into(),
+            self.amount.into(),
+            self.rule.to_string().into(),
+            self.acquisition_date
+                .map(|date| date.format("%d.%m.%Y").to_string())
+                .unwrap_or_default()
+                .into(),
+            self.proceeds.into(),
+            self.allowable_cost.into(),
+            self.gain().into(),
+        ]
+    }
+}
+
+/// Section 104 pool of a single asset.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct Section104Pool {
+    /// Pooled amount.
+    pub amount: Decimal,
+    /// Total allowable cost of the pooled amount.
+    pub cost: Decimal,
+}
+
+impl Section104Pool {
+    /// Elements of the CSV header, matching the `csv_line_elements`.
+    pub const CSV_HEADER_ELEMENTS: [&'static str; 4] =
+        ["Asset", "Amount", "Allowable Cost", "Unit Cost"];
+
+    /// Elements of the single line in the CSV file, describing the pool of the given asset.
+    pub fn csv_line_elements(&self, asset: &AssetType) -> Vec<CsvField> {
+        vec![
+            asset.to_string().into(),
+            self.amount.into(),
+            self.cost.into(),
+            self.unit_cost().unwrap_or_default().into(),
+        ]
+    }
+
+    /// Average unit cost of the pool, `None` if the pool is empty.
+    pub fn unit_cost(&self) -> Option<Decimal> {
+        (!self.amount.is_zero()).then(|| self.cost / self.amount)
+    }
+
+    /// Add the acquired amount to the pool.
+    fn add(&mut self, amount: Decimal, cost: Decimal) {
+        self.amount += amount;
+        self.cost += cost;
+    }
+
+    /// Remove the disposed amount from the pool, returning its allowable cost.
+    ///
+    /// Caller must ensure the pool holds enough.
+    fn remove(&mut self, amount: Decimal) -> Decimal {
+        let cost = if amount == self.amount {
+            self.cost
+        } else {
+            self.cost * amount / self.amount
+        };
+
+        self.amount -= amount;
+        self.cost -= cost;
+        cost
+    }
+}
+
+/// Acquisitions of an asset made on a single day, not matched yet.
+struct Acquisition {
+    date: NaiveDate,
+    /// Amount which isn't matched yet.
+    amount: Decimal,
+    /// Allowable cost of the amount which isn't matched yet.
+    cost: Decimal,
+}
+
+impl Acquisition {
+    /// Add the acquisition to the list, merging it with the acquisition made on the same day.
+    ///
+    /// Acquisitions must be added in order of their dates.
+    fn add_to(self, acquisitions: &mut Vec<Acquisition>) {
+        match acquisitions.last_mut() {
+            Some(last) if last.date == self.date => {
+                last.amount += self.amount;
+                last.cost += self.cost;
+            }
+            _ => acquisitions.push(self),
+        }
+    }
+}
+
+/// Disposals of an asset made on a single day, not matched yet.
+struct Disposal<'t> {
+    /// First disposing transaction of the day, used to report the errors.
+    transaction: &'t Transaction,
+    /// Ordinal numbers of all disposing transactions of the day.
+    ordinals: Vec<u32>,
+    /// Amount which isn't matched yet.
+    amount: Decimal,
+    /// Proceeds of the amount which isn't matched yet.
+    proceeds: Decimal,
+}
+
+impl<'t> Disposal<'t> {
+    /// Create a new disposal of the `amount`, made by the transaction.
+    fn new(transaction: &'t Transaction, amount: Decimal, proceeds: Decimal) -> Self {
+        Disposal {
+            transaction,
+            ordinals: vec![transaction.ordinal()],
+            amount,
+            proceeds,
+        }
+    }
+
+    /// Date of the disposal.
+    fn date(&self) -> NaiveDate {
+        self.transaction.date()
+    }
+
+    /// Add the disposal to the list, merging it with the disposal made on the same day.
+    ///
+    /// Disposals must be added in order of their dates.
+    fn add_to(self, disposals: &mut Vec<Disposal<'t>>) {
+        match disposals.last_mut() {
+            Some(last) if last.date() == self.date() => {
+                last.amount += self.amount;
+                last.proceeds += self.proceeds;
+                for ordinal in self.ordinals {
+                    if !last.ordinals.contains(&ordinal) {
+                        last.ordinals.push(ordinal);
+                    }
+                }
+            }
+            _ => disposals.push(self),
+        }
+    }
+}
+
+/// Take the matched part of the value, proportionally to the matched amount.
+///
+/// Last part takes the entire remaining value, so nothing is lost to the rounding.
+fn take_value(matched: Decimal, amount: Decimal, value: Decimal) -> Decimal {
+    if matched == amount {
+        value
+    } else {
+        value * matched / amount
+    }
+}
+
+/// Ledger of the disposals matched according to the UK share pooling rules.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct UkLedger {
+    /// Matched parts of the disposals, in order of the disposing transactions.
+    disposals: Vec<UkDisposal>,
+    /// Interest income, with the date on which it was received.
+    interest: Vec<(NaiveDate, Decimal)>,
+    /// Section 104 pools after all transactions.
+    pools: BTreeMap<AssetType, Section104Pool>,
+}
+
+impl UkLedger {
+    /// Create a new `UkLedger` instance.
+    ///
+    /// # Arguments
+    /// * `transactions` - A list of validated transactions, in ascending order.
+    /// * `fiat` - Supported fiat currencies, fees paid in them are allowable costs.
+    /// * `prices` - Prices used to value the swaps, only required if there are any.
+    ///
+    /// # Returns
+    /// * `LedgerError` - In case a swap can't be valued, or a disposal exceeds the holdings.
+    pub fn new(
+        transactions: &[Transaction],
+        fiat: &FiatSettings,
+        prices: Option<&PriceTable>,
+    ) -> Result<Self, LedgerError> {
+        let mut acquisitions = BTreeMap::<AssetType, Vec<Acquisition>>::new();
+        let mut disposals = BTreeMap::<AssetType, Vec<Disposal>>::new();
+        let mut ledger = UkLedger::default();
+
+        for transaction in transactions {
+            let (input_token, input_amount) = transaction.input();
+            let (output_token, output_amount) = transaction.output();
+
+            // Fiat fee is an allowable cost, crypto fee is a disposal of its own.
+            let fiat_fee = match transaction.fee() {
+                Some((token, amount)) if fiat.is_fiat(token) => *amount,
+                Some((token, amount)) => {
+                    Disposal::new(transaction, *amount, Decimal::ZERO)
+                        .add_to(disposals.entry(token.clone()).or_default());
+                    Decimal::ZERO
+                }
+                None => Decimal::ZERO,
+            };
+
+            let acquisition = |cost| Acquisition {
+                date: transaction.date(),
+                amount: output_amount,
+                cost,
+            };
+            let disposal = |amount, proceeds| Disposal::new(transaction, amount, proceeds);
+
+            match transaction.tx_type() {
+                TransactionType::Buying | TransactionType::Invoice | TransactionType::Interest => {
+                    if transaction.tx_type() == TransactionType::Interest {
+                        ledger.interest.push((transaction.date(), input_amount));
+                    }
+                    acquisition(input_amount + fiat_fee)
+                        .add_to(acquisitions.entry(output_token).or_default());
+                }
+                TransactionType::Selling => {
+                    disposal(input_amount, output_amount - fiat_fee)
+                        .add_to(disposals.entry(input_token).or_default());
+                }
+                TransactionType::Swap => {
+                    let market_value = Self::market_value(transaction, prices)?;
+                    disposal(input_amount, market_value)
+                        .add_to(disposals.entry(input_token).or_default());
+                    acquisition(market_value + fiat_fee)
+                        .add_to(acquisitions.entry(output_token).or_default());
+                }
+                TransactionType::Transfer => {
+                    let fee_amount = input_amount - output_amount;
+                    if !fee_amount.is_zero() {
+                        disposal(fee_amount, Decimal::ZERO)
+                            .add_to(disposals.entry(input_token).or_default());
+                    }
+                }
+            }
+        }
+
+        for (asset, asset_acquisitions) in acquisitions {
+            let asset_disposals = disposals.remove(&asset).unwrap_or_default();
+            ledger.match_asset(asset, asset_acquisitions, asset_disposals)?;
+        }
+
+        // Disposals of assets which were never acquired.
+        if let Some((asset, disposal)) = disposals
+            .into_iter()
+            .find_map(|(asset, disposals)| disposals.into_iter().next().map(|d| (asset, d)))
+        {
+            return Err(Self::insufficient_holdings(asset, &disposal));
+        }
+
+        // Stable sort, so the parts of the same disposal keep the matching order.
+        ledger
+            .disposals
+            .sort_by(|a, b| (a.date, &a.ordinals).cmp(&(b.date, &b.ordinals)));
+
+        Ok(ledger)
+    }
+
+    /// Create a new `UkLedger` instance, from the transactions made until the end of the cutoff date.
+    ///
+    /// Later transactions are ignored, including the acquisitions which would be matched by the bed & breakfast rule,
+    /// so the pools are the same as they were at the cutoff date.
+    pub fn new_until(
+        transactions: &[Transaction],
+        cutoff: NaiveDate,
+        fiat: &FiatSettings,
+        prices: Option<&PriceTable>,
+    ) -> Result<Self, LedgerError> {
+        let prefix_len = transactions.partition_point(|tx| tx.date() <= cutoff);
+        Self::new(&transactions[..prefix_len], fiat, prices)
+    }
+
+    /// Matched parts of the disposals, in order of the disposing transactions.
+    pub fn disposals(&self) -> &[UkDisposal] {
+        &self.disposals
+    }
+
+    /// Section 104 pools after all transactions, per asset.
+    pub fn pools(&self) -> &BTreeMap<AssetType, Section104Pool> {
+        &self.pools
+    }
+
+    /// Section 104 pools which still hold some of the asset, sorted by asset.
+    pub fn holdings(&self) -> impl Iterator<Item = (&AssetType, &Section104Pool)> {
+        self.pools.iter().filter(|(_, pool)| !pool.amount.is_zero())
+    }
+
+    /// Market value of the swap, in the base currency.
+    ///
+    /// Output asset is valued if its price is known, input asset otherwise.
+    fn market_value(
+        transaction: &Transaction,
+        prices: Option<&PriceTable>,
+    ) -> Result<Decimal, LedgerError> {
+        prices
+            .and_then(|prices| prices.market_value(transaction))
+            .ok_or_else(|| LedgerError {
+                ordinal: transaction.ordinal(),
+                location: transaction.location().clone(),
+                kind: LedgerErrorKind::MissingPrice {
+                    input: transaction.input().0,
+                    output: transaction.output().0,
+                    date: transaction.date(),
+                },
+            })
+    }
+
+    /// Error of a disposal which exceeds the holdings of the asset.
+    fn insufficient_holdings(asset: AssetType, disposal: &Disposal) -> LedgerError {
+        LedgerError {
+            ordinal: disposal.transaction.ordinal(),
+            location: disposal.transaction.location().clone(),
+            kind: LedgerErrorKind::InsufficientLots {
+                holding: HoldingKey::new(asset, None, false),
+                missing: disposal.amount,
+            },
+        }
+    }
+
+    /// Match the disposals of a single asset with its acquisitions.
+    ///
+    /// Both must be sorted by date, with at most one acquisition & one disposal per day.
+    fn match_asset(
+        &mut self,
+        asset: AssetType,
+        mut acquisitions: Vec<Acquisition>,
+        mut disposals: Vec<Disposal>,
+    ) -> Result<(), LedgerError> {
+        // 1. Same-day rule, for all days.
+        for disposal in disposals.iter_mut() {
+            let date = disposal.date();
+            for acquisition in acquisitions
+                .iter_mut()
+                .filter(|acquisition| acquisition.date == date)
+            {
+                self.match_acquisition(&asset, disposal, acquisition, MatchingRule::SameDay);
+            }
+        }
+
+        // 2. & 3. Bed & breakfast rule and the Section 104 pool, in order of the disposals.
+        let mut pool = Section104Pool::default();
+        let mut pooled_idx = 0;
+
+        for disposal in disposals.iter_mut() {
+            let date = disposal.date();
+
+            // Acquisitions made before the disposal can no longer be matched, so they're pooled.
+            while let Some(acquisition) = acquisitions
+                .get_mut(pooled_idx)
+                .filter(|acquisition| acquisition.date < date)
+            {
+                pool.add(acquisition.amount, acquisition.cost);
+                acquisition.amount = Decimal::ZERO;
+                acquisition.cost = Decimal::ZERO;
+                pooled_idx += 1;
+            }
+
+            let last_date = date + Days::new(BED_AND_BREAKFAST_DAYS);
+            for acquisition in acquisitions[pooled_idx..]
+                .iter_mut()
+                .filter(|acquisition| acquisition.date > date && acquisition.date <= last_date)
+            {
+                self.match_acquisition(
+                    &asset,
+                    disposal,
+                    acquisition,
+                    MatchingRule::BedAndBreakfast,
+                );
+            }
+
+            if disposal.amount.is_zero() {
+                continue;
+            }
+
+            if disposal.amount > pool.amount {
+                disposal.amount -= pool.amount;
+                return Err(Self::insufficient_holdings(asset, disposal));
+            }
+
+            let allowable_cost = pool.remove(disposal.amount);
+            self.disposals.push(UkDisposal {
+                ordinals: disposal.ordinals.clone(),
+                date,
+                asset: asset.clone(),
+                amount: disposal.amount,
+                rule: MatchingRule::Section104,
+                acquisition_date: None,
+                proceeds: disposal.proceeds,
+                allowable_cost,
+            });
+            disposal.amount = Decimal::ZERO;
+            disposal.proceeds = Decimal::ZERO;
+        }
+
+        for acquisition in &acquisitions[pooled_idx..] {
+            pool.add(acquisition.amount, acquisition.cost);
+        }
+        self.pools.insert(asset, pool);
+
+        Ok(())
+    }
+
+    /// Match as much of the disposal as possible with the acquisition, using the given rule.
+    fn match_acquisition(
+        &mut self,
+        asset: &AssetType,
+        disposal: &mut Disposal,
+        acquisition: &mut Acquisition,
+        rule: MatchingRule,
+    ) {
+        let matched = disposal.amount.min(acquisition.amount);
+        if matched.is_zero() {
+            return;
+        }
+
+        let proceeds = take_value(matched, disposal.amount, disposal.proceeds);
+        let allowable_cost = take_value(matched, acquisition.amount, acquisition.cost);
+
+        disposal.amount -= matched;
+        disposal.proceeds -= proceeds;
+        acquisition.amount -= matched;
+        acquisition.cost -= allowable_cost;
+
+        self.disposals.push(UkDisposal {
+            ordinals: disposal.ordinals.clone(),
+            date: disposal.date(),
+            asset: asset.clone(),
+            amount: matched,
+            rule,
+            acquisition_date: Some(acquisition.date),
+            proceeds,
+            allowable_cost,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_utils::{asset, date, dec, tx},
+        types::TransactionType::{Buying, Selling},
+    };
+
+    fn uk_ledger(transactions: &[Transaction]) -> UkLedger {
+        UkLedger::new(transactions, &FiatSettings::default(), None).unwrap()
+    }
+
+    #[test]
+    fn same_day_transactions_are_aggregated() {
+        let transactions = [
+            tx(1, "2024-01-01", Buying, ("EUR", "100"), ("BTC", "1")),
+            tx(2, "2024-05-01", Buying, ("EUR", "300"), ("BTC", "1")),
+            tx(3, "2024-05-01", Selling, ("BTC", "0.5"), ("EUR", "200")),
+            tx(4, "2024-05-01", Buying, ("EUR", "500"), ("BTC", "1")),
+            tx(5, "2024-05-01", Selling, ("BTC", "1"), ("EUR", "400")),
+        ];
+        let ledger = uk_ledger(&transactions);
+
+        // Both disposals are matched with the average cost of both acquisitions of the day.
+        assert_eq!(
+            ledger.disposals(),
+            [UkDisposal {
+                ordinals: vec![3, 5],
+                date: date("2024-05-01"),
+                asset: asset("BTC"),
+                amount: dec("1.5"),
+                rule: MatchingRule::SameDay,
+                acquisition_date: Some(date("2024-05-01")),
+                proceeds: dec("600"),
+                allowable_cost: dec("600"),
+            }]
+        );
+        assert_eq!(
+            ledger.pools()[&asset("BTC")],
+            Section104Pool {
+                amount: dec("1.5"),
+                cost: dec("300"),
+            }
+        );
+    }
+
+    #[test]
+    fn bed_and_breakfast_matches_aggregated_acquisitions() {
+        let transactions = [
+            tx(1, "2024-01-01", Buying, ("EUR", "200"), ("BTC", "2")),
+            tx(2, "2024-03-01", Selling, ("BTC", "1"), ("EUR", "300")),
+            tx(3, "2024-03-01", Selling, ("BTC", "0.5"), ("EUR", "150")),
+            tx(4, "2024-03-10", Buying, ("EUR", "100"), ("BTC", "0.5")),
+            tx(5, "2024-03-10", Buying, ("EUR", "200"), ("BTC", "0.5")),
+        ];
+        let ledger = uk_ledger(&transactions);
+
+        let parts: Vec<_> = ledger
+            .disposals()
+            .iter()
+            .map(|part| {
+                (
+                    part.ordinals.clone(),
+                    part.rule,
+                    part.amount,
+                    part.proceeds,
+                    part.allowable_cost,
+                )
+            })
+            .collect();
+        assert_eq!(
+            parts,
+            vec![
+                (
+                    vec![2, 3],
+                    MatchingRule::BedAndBreakfast,
+                    dec("1"),
+                    dec("300"),
+                    dec("300")
+                ),
+                (
+                    vec![2, 3],
+                    MatchingRule::Section104,
+                    dec("0.5"),
+                    dec("150"),
+                    dec("50")
+                ),
+            ]
+        );
+
+        // Same-day disposals are a single disposal in the tax year report.
+        let reports = ledger.tax_year_reports();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].disposals, 1);
+        assert_eq!(reports[0].net_gain(), dec("100"));
+
+        assert_eq!(
+            ledger.pools()[&asset("BTC")],
+            Section104Pool {
+                amount: dec("1.5"),
+                cost: dec("150"),
+            }
+        );
+    }
+
+    #[test]
+    fn section_104_pool_uses_average_cost() {
+        let transactions = [
+            tx(1, "2024-01-01", Buying, ("EUR", "100"), ("BTC", "1")),
+            tx(2, "2024-02-01", Buying, ("EUR", "300"), ("BTC", "1")),
+            tx(3, "2024-06-01", Selling, ("BTC", "1"), ("EUR", "500")),
+            // Acquired 31 days after the disposal, so it's pooled instead.
+            tx(4, "2024-07-02", Buying, ("EUR", "600"), ("BTC", "1")),
+        ];
+        let ledger = uk_ledger(&transactions);
+
+        assert_eq!(ledger.disposals().len(), 1);
+        let disposal = &ledger.disposals()[0];
+        assert_eq!(disposal.rule, MatchingRule::Section104);
+        assert_eq!(disposal.acquisition_date, None);
+        assert_eq!(disposal.allowable_cost, dec("200"));
+        assert_eq!(disposal.gain(), dec("300"));
+
+        let holdings: Vec<_> = ledger.holdings().collect();
+        assert_eq!(
+            holdings,
+            vec![(
+                &asset("BTC"),
+                &Section104Pool {
+                    amount: dec("2"),
+                    cost: dec("800"),
+                }
+            )]
+        );
+        assert_eq!(holdings[0].1.unit_cost(), Some(dec("400")));
+    }
+
+    #[test]
+    fn disposal_exceeding_the_pool_fails() {
+        let transactions = [
+            tx(1, "2024-01-01", Buying, ("EUR", "100"), ("BTC", "1")),
+            tx(2, "2024-02-01", Selling, ("BTC", "1.5"), ("EUR", "300")),
+        ];
+        let error = UkLedger::new(&transactions, &FiatSettings::default(), None).unwrap_err();
+
+        assert_eq!(error.ordinal, 2);
+        assert_eq!(
+            error.kind,
+            LedgerErrorKind::InsufficientLots {
+                holding: HoldingKey::new(asset("BTC"), None, false),
+                missing: dec("0.5"),
+            }
+        );
+    }
+
+    #[test]
+    fn pools_at_the_cutoff_date_ignore_later_transactions() {
+        let transactions = [
+            tx(1, "2024-01-01", Buying, ("EUR", "200"), ("BTC", "2")),
+            tx(2, "2024-03-01", Selling, ("BTC", "1"), ("EUR", "300")),
+            tx(3, "2024-03-10", Buying, ("EUR", "400"), ("BTC", "1")),
+        ];
+        let ledger = UkLedger::new_until(
+            &transactions,
+            date("2024-03-05"),
+            &FiatSettings::default(),
+            None,
+        )
+        .unwrap();
+
+        // Disposal isn't matched with the later acquisition by the bed & breakfast rule yet.
+        assert_eq!(ledger.disposals().len(), 1);
+        assert_eq!(ledger.disposals()[0].rule, MatchingRule::Section104);
+        assert_eq!(
+            ledger.pools()[&asset("BTC")],
+            Section104Pool {
+                amount: dec("1"),
+                cost: dec("100"),
+            }
+        );
+
+        // Transactions made on the cutoff date are included.
+        let ledger = UkLedger::new_until(
+            &transactions,
+            date("2024-03-10"),
+            &FiatSettings::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(ledger, uk_ledger(&transactions));
+    }
+
+    #[test]
+    fn crypto_fee_is_a_disposal_at_zero_proceeds() {
+        let transactions = [
+            tx(1, "2024-01-01", Buying, ("EUR", "200"), ("BTC", "2")),
+            tx(2, "2024-02-01", Buying, ("EUR", "100"), ("ETH", "10"))
+                .new_with_fee(Some((asset("BTC"), dec("0.1")))),
+        ];
+        let ledger = uk_ledger(&transactions);
+
+        assert_eq!(
+            ledger.disposals(),
+            [UkDisposal {
+                ordinals: vec![2],
+                date: date("2024-02-01"),
+                asset: asset("BTC"),
+                amount: dec("0.1"),
+                rule: MatchingRule::Section104,
+                acquisition_date: None,
+                proceeds: Decimal::ZERO,
+                allowable_cost: dec("10"),
+            }]
+        );
+        // Cost of the fee isn't added to the acquisition.
+        assert_eq!(ledger.pools()[&asset("ETH")].cost, dec("100"));
+        assert_eq!(ledger.pools()[&asset("BTC")].amount, dec("1.9"));
+    }
+}
@@ -0,0 +1,117 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Report per UK tax year, which runs from 6 April to 5 April of the following year.
+
+use super::UkLedger;
+use crate::types::FiscalYear;
+
+use chrono::NaiveDate;
+use itertools::Itertools;
+use rust_decimal::Decimal;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+};
+
+/// Capital gains & interest income of a single UK tax year.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct UkTaxYearReport {
+    /// Year in which the tax year starts.
+    pub start_year: i32,
+    /// Number of the disposals, all disposals of an asset made on the same day are counted as one.
+    pub disposals: usize,
+    /// Total disposal proceeds.
+    pub proceeds: Decimal,
+    /// Total allowable costs.
+    pub allowable_costs: Decimal,
+    /// Total gains of the disposals with a gain.
+    pub gains: Decimal,
+    /// Total losses of the disposals with a loss, as a positive amount.
+    pub losses: Decimal,
+    /// Total interest income.
+    pub interest_income: Decimal,
+}
+
+impl UkTaxYearReport {
+    /// Net gain (or loss, if negative) of the tax year.
+    pub fn net_gain(&self) -> Decimal {
+        self.gains - self.losses
+    }
+}
+
+impl Display for UkTaxYearReport {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Tax Year {}: Disposals: {}, Proceeds: {:.2}, Allowable Costs: {:.2}, Gains: {:.2}, Losses: {:.2}, Net Gain: {:.2}, Interest Income: {:.2}",
+            FiscalYear::UK_TAX_YEAR.label(self.start_year),
+            self.disposals,
+            self.proceeds,
+            self.allowable_costs,
+            self.gains,
+            self.losses,
+            self.net_gain(),
+            self.interest_income,
+        )
+    }
+}
+
+impl UkLedger {
+    /// Reports per UK tax year, sorted by year.
+    ///
+    /// Gains & losses are netted per disposal, regardless of the matching rules.
+    pub fn tax_year_reports(&self) -> Vec<UkTaxYearReport> {
+        fn report(
+            reports: &mut BTreeMap<i32, UkTaxYearReport>,
+            date: NaiveDate,
+        ) -> &mut UkTaxYearReport {
+            let start_year = FiscalYear::UK_TAX_YEAR.start_year(date);
+            reports
+                .entry(start_year)
+                .or_insert_with(|| UkTaxYearReport {
+                    start_year,
+                    ..Default::default()
+                })
+        }
+
+        let mut reports = BTreeMap::<i32, UkTaxYearReport>::new();
+        for (_, parts) in &self
+            .disposals
+            .iter()
+            .chunk_by(|disposal| (disposal.date, &disposal.asset))
+        {
+            let parts = parts.collect::<Vec<_>>();
+            let report = report(&mut reports, parts[0].date);
+
+            let gain = parts.iter().map(|part| part.gain()).sum::<Decimal>();
+            if gain.is_sign_negative() {
+                report.losses -= gain;
+            } else {
+                report.gains += gain;
+            }
+
+            report.disposals += 1;
+            report.proceeds += parts.iter().map(|part| part.proceeds).sum::<Decimal>();
+            report.allowable_costs += parts
+                .iter()
+                .map(|part| part.allowable_cost)
+                .sum::<Decimal>();
+        }
+
+        for (date, income) in &self.interest {
+            report(&mut reports, *date).interest_income += income;
+        }
+
+        reports.into_values().collect()
+    }
+}
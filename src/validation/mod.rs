@@ -14,50 +14,71 @@ use chrono::NaiveDate;
 use rust_decimal::Decimal;
 use std::collections::{hash_map::Entry, HashMap};
 
-use crate::types::{AssetType, Transaction, TransactionType};
+use crate::types::{
+    FiatSettings, HoldingKey, Transaction, TransactionType, ValidationError,
+    ValidationErrorKind::{self, *},
+    ValidationErrors,
+};
 
 /// Validate the transactions, and return the final state of the ledger.
 /// There are several checks performed:
 /// 1. The ordinal number should be sequential, starting at one and increasing by one.
 /// 2. The dates should be monotonically increasing.
-/// 3. The input amount should be subtracted from the state, and shouldn't result in a negative balance
-///    (with a small tolerance for floating point errors & missing fees entries).
+/// 3. The fee & input amount should be subtracted from the state, and shouldn't result in a negative balance
+///    Amounts are exact decimals, so even the smallest shortfall is reported.
 /// 4. The output amount should be added to the state, without any overflow.
 ///
+/// In case holdings are tracked per account, balances are checked per account as well.
+/// Moving assets between accounts then requires an explicit transfer.
+///
+/// By default, validation stops at the first invalid transaction.
+/// If `collect_all` is set, all transactions are validated, and all errors are returned together.
+/// To keep the errors of the subsequent transactions meaningful, a negative balance is clamped to zero.
+///
 /// # Arguments
 /// * `transaction` - A list of transactions to validate, in ascending order.
-/// * `init_state` - Initial state of the ledger, before the first transaction is applied.
+/// * `fiat` - Supported fiat currencies, balances are only tracked for the other assets.
+/// * `per_account` - Whether the balances are tracked per account.
+/// * `collect_all` - Whether to continue validation after the first invalid transaction.
 ///
 /// # Returns
-/// * `HashMap<AssetType, Decimal>` - If the transactions are valid, return the final state of the ledger.
-/// * `String` - If the transactions are invalid, return an error message.
+/// * `HashMap<HoldingKey, Decimal>` - If the transactions are valid, return the final state of the ledger.
+/// * `ValidationErrors` - If the transactions are invalid, return the validation failures.
 pub fn context_validation(
     transactions: &Vec<Transaction>,
-) -> Result<HashMap<AssetType, Decimal>, String> {
+    fiat: &FiatSettings,
+    per_account: bool,
+    collect_all: bool,
+) -> Result<HashMap<HoldingKey, Decimal>, ValidationErrors> {
     let mut previous_ordinal = 0;
     let mut previous_date = NaiveDate::MIN;
-    let mut state = HashMap::<AssetType, Decimal>::default();
+    let mut state = HashMap::<HoldingKey, Decimal>::default();
+    let mut errors = Vec::new();
 
     for tx in transactions {
+        let mut report = |kind: ValidationErrorKind| {
+            errors.push(ValidationError {
+                ordinal: tx.ordinal(),
+                location: tx.location().clone(),
+                kind,
+            })
+        };
+
         // 1. Validate the ordinal number.
         if tx.ordinal() != previous_ordinal + 1 {
-            return Err(format!(
-                "Context: {}; Ordinal number mismatch: expected {}, found {}",
-                tx.extra_info(),
-                previous_ordinal + 1,
-                tx.ordinal()
-            ));
+            report(OrdinalMismatch {
+                expected: previous_ordinal + 1,
+                found: tx.ordinal(),
+            });
         }
         previous_ordinal = tx.ordinal();
 
         // 2. Validate the date.
         if tx.date() < previous_date {
-            return Err(format!(
-                "Context {}; Date mismatch: expected >= {:?}, found {:?}",
-                tx.extra_info(),
-                previous_date,
-                tx.date()
-            ));
+            report(DateOrder {
+                previous: previous_date,
+                found: tx.date(),
+            });
         }
         previous_date = tx.date();
 
@@ -66,66 +87,49 @@ pub fn context_validation(
         let (output_token, output_amount) = tx.output();
 
         if input_amount.is_zero() {
-            return Err(format!(
-                "Context: {}; Input amount is zero for transaction: {:?}",
-                tx.extra_info(),
-                tx
-            ));
+            report(ZeroInputAmount {
+                tx_type: tx.tx_type(),
+            });
         }
 
-        // 3.1. Subtract the input amount in case it's not fiat.
-        if input_token.is_crypto() {
-            match state.entry(input_token.clone()) {
-                Entry::Occupied(mut entry) => {
-                    let entry = entry.get_mut();
-
-                    if let Some(new_value) = entry.checked_sub(input_amount) {
-                        if new_value < Decimal::ZERO {
-                            return Err(format!(
-                                "Context: {}; Negative balance of {} for {:?} after transaction: {:?}. State dump: {:?}",
-                                tx.extra_info(),
-                                new_value, input_token, tx, state
-                            ));
-                        }
-
-                        *entry = new_value;
-                    } else {
-                        // This part should never happen, since `Decimal` supports negative numbers.
-                        return Err(format!(
-                            "Context: {}; Underflow for {:?} after transaction: {:?}",
-                            tx.extra_info(),
-                            input_token,
-                            tx
-                        ));
-                    }
-                }
-                Entry::Vacant(_) => {
-                    return Err(format!(
-                        "Context: {}; Token {:?} not found in state for transaction: {:?}",
-                        tx.extra_info(),
-                        input_token,
-                        tx
-                    ));
+        // 3.1. Subtract the fee in case it's not fiat. Fee is paid before the transaction is made.
+        if let Some((fee_token, fee_amount)) = tx.fee() {
+            if fee_amount.is_sign_negative() {
+                report(NegativeFee {
+                    token: fee_token.clone(),
+                    amount: *fee_amount,
+                });
+            } else if fiat.is_crypto(fee_token) {
+                let fee_key = HoldingKey::new(fee_token.clone(), tx.account(), per_account);
+                if let Err(kind) = subtract(&mut state, fee_key, *fee_amount) {
+                    report(kind);
                 }
             }
         }
 
-        // 3.2. Add the output amount in case it's not fiat.
-        if output_token.is_crypto() {
-            match state.entry(output_token.clone()) {
+        // 3.2. Subtract the input amount in case it's not fiat.
+        if fiat.is_crypto(&input_token) {
+            let input_key = HoldingKey::new(input_token.clone(), tx.account(), per_account);
+            if let Err(kind) = subtract(&mut state, input_key, input_amount) {
+                report(kind);
+            }
+        }
+
+        // 3.3. Add the output amount in case it's not fiat.
+        if fiat.is_crypto(&output_token) {
+            let output_key =
+                HoldingKey::new(output_token.clone(), tx.receiving_account(), per_account);
+            match state.entry(output_key.clone()) {
                 Entry::Occupied(mut entry) => {
                     let entry = entry.get_mut();
 
-                    let new_value = entry.checked_add(output_amount).ok_or_else(|| {
-                        format!(
-                            "Context: {}; Overflow for {:?} after transaction: {:?}.",
-                            tx.extra_info(),
-                            output_token,
-                            tx
-                        )
-                    })?;
-
-                    *entry = new_value;
+                    match entry.checked_add(output_amount) {
+                        Some(new_value) => *entry = new_value,
+                        None => report(Overflow {
+                            holding: output_key,
+                            amount: output_amount,
+                        }),
+                    }
                 }
                 Entry::Vacant(entry) => {
                     entry.insert(output_amount);
@@ -134,231 +138,361 @@ pub fn context_validation(
         }
 
         // 4. Specific tx type validation
-        match tx.tx_type() {
-            TransactionType::Interest => {
-                validate_interest_transaction(tx)?;
-            }
-            TransactionType::Invoice => {
-                validate_invoice_transaction(tx)?;
-            }
-            TransactionType::Swap => {
-                validate_swap_transaction(tx)?;
-            }
-            TransactionType::Buying => {
-                validate_buy_transaction(tx)?;
-            }
-            TransactionType::Selling => {
-                validate_selling_transaction(tx)?;
-            }
+        let result = match tx.tx_type() {
+            TransactionType::Interest => validate_interest_transaction(tx, fiat),
+            TransactionType::Invoice => validate_invoice_transaction(tx, fiat),
+            TransactionType::Swap => validate_swap_transaction(tx, fiat),
+            TransactionType::Buying => validate_buy_transaction(tx, fiat),
+            TransactionType::Selling => validate_selling_transaction(tx, fiat),
+            TransactionType::Transfer => validate_transfer_transaction(tx, fiat),
+        };
+        if let Err(kind) = result {
+            report(kind);
+        }
+
+        if !collect_all && !errors.is_empty() {
+            break;
         }
     }
 
-    Ok(state)
+    if errors.is_empty() {
+        Ok(state)
+    } else {
+        Err(ValidationErrors(errors))
+    }
 }
 
+/// Subtract the amount from the holding.
+///
+/// In case the holding doesn't have enough balance, it's clamped to zero,
+/// so the following transactions are validated against a sensible balance.
+fn subtract(
+    state: &mut HashMap<HoldingKey, Decimal>,
+    key: HoldingKey,
+    amount: Decimal,
+) -> Result<(), ValidationErrorKind> {
+    match state.entry(key.clone()) {
+        Entry::Occupied(mut entry) => {
+            let entry = entry.get_mut();
+
+            // `Decimal` supports negative numbers, so the subtraction can only fail on overflow.
+            match entry.checked_sub(amount) {
+                Some(new_value) if new_value >= Decimal::ZERO => {
+                    *entry = new_value;
+                    Ok(())
+                }
+                _ => {
+                    let available = std::mem::replace(entry, Decimal::ZERO);
+                    Err(NegativeBalance {
+                        holding: key,
+                        available,
+                        required: amount,
+                    })
+                }
+            }
+        }
+        Entry::Vacant(entry) => {
+            entry.insert(Decimal::ZERO);
+            Err(UnknownHolding {
+                holding: key,
+                required: amount,
+            })
+        }
+    }
+}
+
+// NOTE: The type-specific validations don't check the zero input amount, since it's checked for all transactions.
+
 /// Validate interest transaction specifics.
-fn validate_interest_transaction(tx: &Transaction) -> Result<(), String> {
-    let (input_token, input_amount) = tx.input();
-    let (output_token, output_amount) = tx.output();
+fn validate_interest_transaction(
+    tx: &Transaction,
+    fiat: &FiatSettings,
+) -> Result<(), ValidationErrorKind> {
+    validate_fiat_to_crypto(tx, fiat)
+}
 
-    if !input_token.is_fiat() {
-        return Err(format!(
-            "Context: {}; Interest transaction should have fiat (EUR) input, found {:?} in transaction: {:?}",
-            tx.extra_info(),
-            input_token,
-            tx
-        ));
-    }
+fn validate_invoice_transaction(
+    tx: &Transaction,
+    fiat: &FiatSettings,
+) -> Result<(), ValidationErrorKind> {
+    validate_fiat_to_crypto(tx, fiat)
+}
 
-    if input_amount.is_zero() {
-        return Err(format!(
-            "Context: {}; Interest transaction should have non-zero fiat input amount in transaction: {:?}",
-            tx.extra_info(),
-            tx
-        ));
+fn validate_buy_transaction(
+    tx: &Transaction,
+    fiat: &FiatSettings,
+) -> Result<(), ValidationErrorKind> {
+    validate_fiat_to_crypto(tx, fiat)
+}
+
+/// Validate the transaction which exchanges fiat for a non-zero crypto amount.
+fn validate_fiat_to_crypto(
+    tx: &Transaction,
+    fiat: &FiatSettings,
+) -> Result<(), ValidationErrorKind> {
+    let (input_token, _input_amount) = tx.input();
+    let (output_token, output_amount) = tx.output();
+    let tx_type = tx.tx_type();
+
+    if !fiat.is_fiat(&input_token) {
+        return Err(InputNotFiat {
+            tx_type,
+            found: input_token,
+        });
     }
 
-    if output_token.is_fiat() {
-        return Err(format!(
-            "Context: {}; Interest transaction does not support fiat output, found in transaction: {:?}",
-            tx.extra_info(),
-            tx
-        ));
+    if fiat.is_fiat(&output_token) {
+        return Err(OutputIsFiat {
+            tx_type,
+            found: output_token,
+        });
     }
 
     if output_amount.is_zero() {
-        return Err(format!(
-            "Context: {}; Interest transaction should have non-zero output amount in transaction: {:?}",
-            tx.extra_info(),
-            tx
-        ));
+        return Err(ZeroOutputAmount { tx_type });
     }
 
     Ok(())
 }
 
-fn validate_invoice_transaction(tx: &Transaction) -> Result<(), String> {
-    let (input_token, input_amount) = tx.input();
+fn validate_swap_transaction(
+    tx: &Transaction,
+    fiat: &FiatSettings,
+) -> Result<(), ValidationErrorKind> {
+    let (input_token, _input_amount) = tx.input();
     let (output_token, output_amount) = tx.output();
+    let tx_type = tx.tx_type();
 
-    if !input_token.is_fiat() {
-        return Err(format!(
-            "Context: {}; Invoice transaction should have fiat (EUR) input, found {:?} in transaction: {:?}",
-            tx.extra_info(),
-            input_token,
-            tx
-        ));
+    if fiat.is_fiat(&input_token) {
+        return Err(InputIsFiat {
+            tx_type,
+            found: input_token,
+        });
     }
 
-    if input_amount.is_zero() {
-        return Err(format!(
-            "Context: {}; Invoice transaction should have non-zero fiat input amount in transaction: {:?}",
-            tx.extra_info(),
-            tx
-        ));
+    if fiat.is_fiat(&output_token) {
+        return Err(OutputIsFiat {
+            tx_type,
+            found: output_token,
+        });
     }
 
-    if output_token.is_fiat() {
-        return Err(format!(
-            "Context: {}; Invoice transaction does not support fiat output, found in transaction: {:?}",
-            tx.extra_info(),
-            tx
-        ));
+    if output_amount.is_zero() {
+        return Err(ZeroOutputAmount { tx_type });
     }
 
-    if output_amount.is_zero() {
-        return Err(format!(
-            "Context: {}; Invoice transaction should have non-zero output amount in transaction: {:?}",
-            tx.extra_info(),
-            tx
-        ));
+    if input_token == output_token {
+        return Err(SameTokens {
+            tx_type,
+            token: input_token,
+        });
     }
 
     Ok(())
 }
 
-fn validate_swap_transaction(tx: &Transaction) -> Result<(), String> {
-    let (input_token, input_amount) = tx.input();
-    let (output_token, output_amount) = tx.output();
-
-    if input_token.is_fiat() {
-        return Err(format!(
-            "Context: {}; Swap transaction should not have fiat input, found {:?} in transaction: {:?}",
-            tx.extra_info(),
-            input_token,
-            tx
-        ));
-    }
-
-    if input_amount.is_zero() {
-        return Err(format!(
-            "Context: {}; Swap transaction should have non-zero input amount in transaction: {:?}",
-            tx.extra_info(),
-            tx
-        ));
-    }
+fn validate_selling_transaction(
+    tx: &Transaction,
+    fiat: &FiatSettings,
+) -> Result<(), ValidationErrorKind> {
+    let (input_token, _input_amount) = tx.input();
+    let (output_token, _output_amount) = tx.output();
+    let tx_type = tx.tx_type();
 
-    if output_token.is_fiat() {
-        return Err(format!(
-            "Context: {}; Swap transaction should not have fiat output, found {:?} in transaction: {:?}",
-            tx.extra_info(),
-            output_token,
-            tx
-        ));
+    if fiat.is_fiat(&input_token) {
+        return Err(InputIsFiat {
+            tx_type,
+            found: input_token,
+        });
     }
 
-    if output_amount.is_zero() {
-        return Err(format!(
-            "Context: {}; Swap transaction should have non-zero output amount in transaction: {:?}",
-            tx.extra_info(),
-            tx
-        ));
+    if !fiat.is_fiat(&output_token) {
+        return Err(OutputNotFiat {
+            tx_type,
+            found: output_token,
+        });
     }
 
-    if input_token == output_token {
-        return Err(format!(
-            "Context: {}; Swap transaction should have different input and output tokens, found {:?} in transaction: {:?}",
-            tx.extra_info(),
-            input_token,
-            tx
-        ));
-    }
+    // It is ok to have zero output amount, that is used to represent things like fees.
 
     Ok(())
 }
 
-fn validate_buy_transaction(tx: &Transaction) -> Result<(), String> {
+fn validate_transfer_transaction(
+    tx: &Transaction,
+    fiat: &FiatSettings,
+) -> Result<(), ValidationErrorKind> {
     let (input_token, input_amount) = tx.input();
     let (output_token, output_amount) = tx.output();
+    let tx_type = tx.tx_type();
 
-    if !input_token.is_fiat() {
-        return Err(format!(
-            "Context: {}; Buy transaction should have fiat (EUR) input, found {:?} in transaction: {:?}",
-            tx.extra_info(),
-            input_token,
-            tx
-        ));
+    if !fiat.is_crypto(&input_token) {
+        return Err(InputNotCrypto {
+            tx_type,
+            found: input_token,
+        });
     }
 
-    if input_amount.is_zero() {
-        return Err(format!(
-            "Context: {}; Buy transaction should have non-zero fiat input amount in transaction: {:?}",
-            tx.extra_info(),
-            tx
-        ));
+    if input_token != output_token {
+        return Err(DifferentTokens {
+            tx_type,
+            input: input_token,
+            output: output_token,
+        });
     }
 
-    if output_token.is_fiat() {
-        return Err(format!(
-            "Context: {}; Buy transaction should not have fiat output, found {:?} in transaction: {:?}",
-            tx.extra_info(),
-            output_token,
-            tx
-        ));
+    if output_amount.is_zero() {
+        return Err(ZeroOutputAmount { tx_type });
     }
 
-    if output_amount.is_zero() {
-        return Err(format!(
-            "Context: {}; Buy transaction should have non-zero output amount in transaction: {:?}",
-            tx.extra_info(),
-            tx
-        ));
+    // The difference between the input & output amount is the fee, it cannot be negative.
+    if output_amount > input_amount {
+        return Err(OutputExceedsInput {
+            tx_type,
+            input: input_amount,
+            output: output_amount,
+        });
     }
 
     Ok(())
 }
 
-fn validate_selling_transaction(tx: &Transaction) -> Result<(), String> {
-    let (input_token, input_amount) = tx.input();
-    let (output_token, _output_amount) = tx.output();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{asset, date, dec, tx};
+    use crate::types::TransactionType::{Buying, Selling, Swap, Transfer};
+
+    fn validate(
+        transactions: &Vec<Transaction>,
+        collect_all: bool,
+    ) -> Result<HashMap<HoldingKey, Decimal>, ValidationErrors> {
+        context_validation(transactions, &FiatSettings::default(), false, collect_all)
+    }
 
-    if input_token.is_fiat() {
-        return Err(format!(
-            "Context: {}; Sell transaction should not have fiat input, found {:?} in transaction: {:?}",
-            tx.extra_info(),
-            input_token,
-            tx
-        ));
+    fn btc() -> HoldingKey {
+        HoldingKey::new(asset("BTC"), None, false)
     }
 
-    if input_amount.is_zero() {
-        return Err(format!(
-            "Context: {}; Sell transaction should have non-zero input amount in transaction: {:?}",
-            tx.extra_info(),
-            tx
-        ));
+    #[test]
+    fn valid_transactions_return_the_final_state() {
+        let transactions = vec![
+            tx(1, "2024-01-01", Buying, ("EUR", "100"), ("BTC", "1")),
+            tx(2, "2024-01-02", Swap, ("BTC", "0.5"), ("ETH", "8")),
+            tx(3, "2024-01-03", Selling, ("ETH", "8"), ("EUR", "120")),
+        ];
+        let state = validate(&transactions, false).unwrap();
+
+        assert_eq!(state.len(), 2);
+        assert_eq!(state[&btc()], dec("0.5"));
+        assert_eq!(state[&HoldingKey::new(asset("ETH"), None, false)], dec("0"));
     }
 
-    if !output_token.is_fiat() {
-        return Err(format!(
-            "Context: {}; Sell transaction should have fiat (EUR) output, found {:?} in transaction: {:?}",
-            tx.extra_info(),
-            output_token,
-            tx
-        ));
+    #[test]
+    fn validation_stops_at_the_first_invalid_transaction() {
+        let transactions = vec![
+            tx(1, "2024-01-01", Buying, ("EUR", "100"), ("BTC", "1")),
+            tx(2, "2024-01-02", Selling, ("BTC", "2"), ("EUR", "200")),
+            tx(4, "2024-01-03", Selling, ("ETH", "1"), ("EUR", "10")),
+        ];
+        let ValidationErrors(errors) = validate(&transactions, false).unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].ordinal, 2);
+        assert_eq!(
+            errors[0].kind,
+            NegativeBalance {
+                holding: btc(),
+                available: dec("1"),
+                required: dec("2"),
+            }
+        );
     }
 
-    // It is ok to have zero output amount, that is used to represent things like fees.
+    #[test]
+    fn all_errors_are_collected_in_one_pass() {
+        let transactions = vec![
+            tx(1, "2024-01-02", Buying, ("EUR", "100"), ("BTC", "1")),
+            tx(2, "2024-01-02", Selling, ("BTC", "2"), ("EUR", "200")),
+            tx(4, "2024-01-01", Selling, ("ETH", "1"), ("EUR", "10")),
+            // Balance was clamped to zero, so the following transactions are valid again.
+            tx(5, "2024-01-03", Buying, ("EUR", "100"), ("BTC", "1")),
+            tx(6, "2024-01-04", Transfer, ("BTC", "1"), ("BTC", "1")),
+        ];
+        let ValidationErrors(errors) = validate(&transactions, true).unwrap_err();
+
+        let found: Vec<_> = errors
+            .iter()
+            .map(|error| (error.ordinal, error.kind.clone()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    2,
+                    NegativeBalance {
+                        holding: btc(),
+                        available: dec("1"),
+                        required: dec("2"),
+                    }
+                ),
+                (
+                    4,
+                    OrdinalMismatch {
+                        expected: 3,
+                        found: 4
+                    }
+                ),
+                (
+                    4,
+                    DateOrder {
+                        previous: date("2024-01-02"),
+                        found: date("2024-01-01"),
+                    }
+                ),
+                (
+                    4,
+                    UnknownHolding {
+                        holding: HoldingKey::new(asset("ETH"), None, false),
+                        required: dec("1"),
+                    }
+                ),
+            ]
+        );
 
-    Ok(())
+        let report = ValidationErrors(errors).to_string();
+        assert!(report.starts_with(
+            "Found 4 validation error(s):\nFile: 'test.csv':\n  Row 3, Transaction 2: "
+        ));
+    }
+
+    #[test]
+    fn balances_are_checked_per_account() {
+        let kraken = || Some("Kraken".to_string());
+        let ledger = || Some("Ledger".to_string());
+        let transactions = vec![
+            tx(1, "2024-01-01", Buying, ("EUR", "100"), ("BTC", "1"))
+                .new_with_accounts(kraken(), None),
+            tx(2, "2024-01-02", Transfer, ("BTC", "0.4"), ("BTC", "0.4"))
+                .new_with_accounts(kraken(), ledger()),
+            tx(3, "2024-01-03", Selling, ("BTC", "0.5"), ("EUR", "60"))
+                .new_with_accounts(ledger(), None),
+        ];
+
+        // All accounts together hold enough BTC.
+        assert!(validate(&transactions, false).is_ok());
+
+        let ValidationErrors(errors) =
+            context_validation(&transactions, &FiatSettings::default(), true, false).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].ordinal, 3);
+        assert_eq!(
+            errors[0].kind,
+            NegativeBalance {
+                holding: HoldingKey::new(asset("BTC"), Some("Ledger"), true),
+                available: dec("0.4"),
+                required: dec("0.5"),
+            }
+        );
+    }
 }